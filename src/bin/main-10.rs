@@ -1,8 +1,4 @@
-use std::{
-    cmp::Ordering,
-    collections::{BinaryHeap, HashSet},
-    fs,
-};
+use std::{collections::HashSet, env, fs};
 
 fn main() {
     let filename = "inputs/10.txt";
@@ -11,65 +7,20 @@ fn main() {
 
     let mut map = parse_sketch(&content).expect("Should have been able to parse sketch");
 
-    // bfs from animal position without repeated visits
     let animal_position = find_animal(&map).expect("Could not find animal position");
-    let animal_tile = determine_animal_tile(&map, animal_position);
-    map[animal_position.y][animal_position.x] = animal_tile;
-    let mut stack = BinaryHeap::new();
-    let mut visited = HashSet::new();
-    stack.push(State {
-        steps: 0,
-        heuristic: 0,
-        position: animal_position,
-    });
-    let mut max_steps = 0;
-    while let Some(current_state) = stack.pop() {
-        if visited.contains(&current_state.position) {
-            continue;
-        }
-        visited.insert(current_state.position);
-        max_steps = std::cmp::max(max_steps, current_state.steps);
-        let neighbors = get_neighbors(&map, current_state.position);
-        for n in neighbors {
-            stack.push(State {
-                steps: current_state.steps + 1,
-                heuristic: 0,
-                position: n,
-            });
-        }
-    }
+    let main_loop = find_main_loop(&mut map, animal_position)
+        .expect("Could not find a loop through the animal position");
+    let max_steps = main_loop.len() / 2;
     println!("It takes {max_steps} steps along the loop to get from the starting position to the point farthest from the starting position.");
 
-    // idea: subdivide each tile into nice subtiles, interpret main-loop pipes
-    // as walls, do a shortest path search towards the sides of the map for each
-    // (nonloop) tile
-    let mut subtile_map = vec![vec![SubTile::Open; 3 * map[0].len()]; 3 * map.len()];
-    for position in visited.iter() {
-        let shape = map[position.y][position.x].get_subtile_shape();
-        for dy in 0..3 {
-            for dx in 0..3 {
-                subtile_map[3 * position.y + dy][3 * position.x + dx] = shape[dy][dx];
-            }
-        }
-    }
-
-    let mut enclosed_positions = HashSet::new();
-    for (i, row) in map.iter().enumerate() {
-        for (j, _) in row.iter().enumerate() {
-            let position = Position { y: i, x: j };
-            if visited.contains(&position) {
-                continue;
-            }
-            if !is_connected_to_boundary(&subtile_map, position, &enclosed_positions) {
-                enclosed_positions.insert(position);
-            }
-        }
-    }
-
-    println!(
-        "{} tiles are enclosed by the loop.",
-        enclosed_positions.len()
-    );
+    // either derive the count from the area of the loop, or scan each row and
+    // count the tiles behind an odd number of loop crossings (`--scanline`)
+    let enclosed = if env::args().skip(1).any(|a| a == "--scanline") {
+        count_enclosed_by_scanline(&map, &main_loop.path)
+    } else {
+        count_enclosed_by_area(&main_loop.path)
+    };
+    println!("{enclosed} tiles are enclosed by the loop.");
 }
 
 fn parse_sketch(content: &str) -> Result<Vec<Vec<Tile>>, TileParseError> {
@@ -119,43 +70,6 @@ impl Tile {
             }
         }
     }
-
-    fn get_subtile_shape(&self) -> [[SubTile; 3]; 3] {
-        match self {
-            Self::Vertical => [[SubTile::Open, SubTile::Closed, SubTile::Open]; 3],
-            Self::Horizontal => [
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-                [SubTile::Closed, SubTile::Closed, SubTile::Closed],
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-            ],
-            Self::NE => [
-                [SubTile::Open, SubTile::Closed, SubTile::Open],
-                [SubTile::Open, SubTile::Closed, SubTile::Closed],
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-            ],
-            Self::NW => [
-                [SubTile::Open, SubTile::Closed, SubTile::Open],
-                [SubTile::Closed, SubTile::Closed, SubTile::Open],
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-            ],
-            Self::SW => [
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-                [SubTile::Closed, SubTile::Closed, SubTile::Open],
-                [SubTile::Open, SubTile::Closed, SubTile::Open],
-            ],
-            Self::SE => [
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-                [SubTile::Open, SubTile::Closed, SubTile::Closed],
-                [SubTile::Open, SubTile::Closed, SubTile::Open],
-            ],
-            Self::Ground => [
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-                [SubTile::Open, SubTile::Open, SubTile::Open],
-            ],
-            Self::Animal => panic!(r"who knows? ¯\_(ツ)_/¯"),
-        }
-    }
 }
 
 enum RelativeLocation {
@@ -206,60 +120,45 @@ struct Position {
     y: usize,
 }
 
-fn determine_animal_tile(map: &[Vec<Tile>], position: Position) -> Tile {
-    let connected_to_up = HashSet::from([Tile::Vertical, Tile::NW, Tile::NE]);
-    let connected_to_down = HashSet::from([Tile::Vertical, Tile::SW, Tile::SE]);
-    let connected_to_left = HashSet::from([Tile::Horizontal, Tile::NW, Tile::SW]);
-    let connected_to_right = HashSet::from([Tile::Horizontal, Tile::NE, Tile::SE]);
-
-    let up_tile = map[position.y - 1][position.x];
-    let down_tile = map[position.y + 1][position.x];
-    let left_tile = map[position.y][position.x - 1];
-    let right_tile = map[position.y][position.x + 1];
-
-    let xyz = [
-        connected_to_down.contains(&up_tile),
-        connected_to_left.contains(&right_tile),
-        connected_to_up.contains(&down_tile),
-        connected_to_right.contains(&left_tile),
-    ];
-
-    match xyz {
-        [true, true, false, false] => Tile::NE,
-        [true, false, true, false] => Tile::Vertical,
-        [true, false, false, true] => Tile::NW,
-        [false, true, true, false] => Tile::SE,
-        [false, true, false, true] => Tile::Horizontal,
-        [false, false, true, true] => Tile::SW,
-        _ => unreachable!(),
-    }
-}
-
-#[derive(Debug, Eq)]
-struct State {
-    steps: usize,
-    heuristic: usize,
-    position: Position,
-}
+const PIPES: [Tile; 6] = [
+    Tile::Vertical,
+    Tile::Horizontal,
+    Tile::NE,
+    Tile::NW,
+    Tile::SW,
+    Tile::SE,
+];
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // turn it into a min-heap by switching order of `self` and `other`
-        // (https://doc.rust-lang.org/std/collections/struct.BinaryHeap.html)
-        (other.steps + other.heuristic).cmp(&(self.steps + self.heuristic))
-    }
+#[derive(Debug)]
+struct MainLoop {
+    // ordered cycle of positions, beginning at the animal
+    path: Vec<Position>,
 }
 
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl MainLoop {
+    fn len(&self) -> usize {
+        self.path.len()
     }
 }
 
-impl PartialEq for State {
-    fn eq(&self, other: &Self) -> bool {
-        self.steps == other.steps && self.heuristic == other.heuristic
+#[derive(Debug)]
+struct NoLoopError;
+
+// replaces the animal with a pipe that connects to both of its neighbors and
+// closes a loop; if several pipes fit, e.g. because three neighbors point at
+// the animal, the first one whose loop closes wins
+fn find_main_loop(map: &mut [Vec<Tile>], start: Position) -> Result<MainLoop, NoLoopError> {
+    for start_tile in PIPES {
+        map[start.y][start.x] = start_tile;
+        if get_neighbors(map, start).len() != 2 {
+            continue;
+        }
+        if let Some(path) = trace_loop(map, start) {
+            return Ok(MainLoop { path });
+        }
     }
+    map[start.y][start.x] = Tile::Animal;
+    Err(NoLoopError)
 }
 
 fn get_neighbors(map: &[Vec<Tile>], position: Position) -> Vec<Position> {
@@ -301,7 +200,7 @@ fn get_neighbors(map: &[Vec<Tile>], position: Position) -> Vec<Position> {
     }
 
     // move right
-    if position.x + 1 < map.len() {
+    if position.x + 1 < map[position.y].len() {
         let next_tile = &map[position.y][position.x + 1];
         if current_tile.is_connected_to(next_tile, RelativeLocation::Right) {
             neighbors.push(Position {
@@ -314,101 +213,58 @@ fn get_neighbors(map: &[Vec<Tile>], position: Position) -> Vec<Position> {
     neighbors
 }
 
-fn is_connected_to_boundary(
-    map: &Vec<Vec<SubTile>>,
-    position: Position,
-    enclosed_positions: &HashSet<Position>,
-) -> bool {
-    let mut stack = BinaryHeap::new();
-    let mut visited = HashSet::new();
-    stack.push(State {
-        steps: 0,
-        heuristic: compute_heuristic(map, position),
-        position: Position {
-            y: 3 * position.y,
-            x: 3 * position.x,
-        },
-    });
-    while let Some(current_state) = stack.pop() {
-        if visited.contains(&current_state.position) {
-            continue;
-        }
-        visited.insert(current_state.position);
-
-        if current_state.position.y == 0
-            || current_state.position.y + 1 == map.len()
-            || current_state.position.x == 0
-            || current_state.position.x + 1 == map[0].len()
-        {
-            return true;
-        }
-
-        // if we encounter an enclosed position, we know that there's NO WAY
-        // OUT! since `enclosed_positions` is not storing subtile positions, we
-        // first need to "coarse grain" again!
-        let tile_position = Position {
-            y: current_state.position.y / 3,
-            x: current_state.position.x / 3,
-        };
-        if enclosed_positions.contains(&tile_position) {
-            return false;
-        }
-
-        let neighbors = get_subtile_neighbors(map, current_state.position);
-        for position in neighbors {
-            stack.push(State {
-                steps: current_state.steps + 1,
-                heuristic: compute_heuristic(map, position),
-                position,
-            });
-        }
+// follow the pipes from `start` until they lead back to it; `None` if they
+// end in a pipe that connects to nothing else
+fn trace_loop(map: &[Vec<Tile>], start: Position) -> Option<Vec<Position>> {
+    let mut path = vec![start];
+    let mut previous = start;
+    let mut current = *get_neighbors(map, start).first()?;
+    while current != start {
+        path.push(current);
+        let next = get_neighbors(map, current)
+            .into_iter()
+            .find(|&n| n != previous)?;
+        previous = current;
+        current = next;
     }
-    false
-}
-
-fn compute_heuristic(map: &Vec<Vec<SubTile>>, position: Position) -> usize {
-    [
-        position.y,
-        position.x,
-        map.len() - position.y,
-        map[0].len() - position.x,
-    ]
-    .into_iter()
-    .min()
-    .unwrap()
+    Some(path)
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum SubTile {
-    Open,
-    Closed,
+// the shoelace formula gives the area `A` of the polygon through the centers
+// of the loop tiles; by pick's theorem, `A = I + B / 2 - 1`, where the `B`
+// boundary points are the loop tiles and the `I` interior points are exactly
+// the enclosed tiles
+fn count_enclosed_by_area(cycle: &[Position]) -> usize {
+    let twice_area = cycle
+        .iter()
+        .zip(cycle.iter().cycle().skip(1))
+        .map(|(p, q)| (p.x * q.y) as i64 - (q.x * p.y) as i64)
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+    (twice_area + 2 - cycle.len()) / 2
 }
 
-fn get_subtile_neighbors(map: &[Vec<SubTile>], position: Position) -> Vec<Position> {
-    let mut neighbors = Vec::new();
-    if position.y > 0 && map[position.y - 1][position.x] != SubTile::Closed {
-        neighbors.push(Position {
-            y: position.y - 1,
-            x: position.x,
-        });
-    }
-    if position.y + 1 < map.len() && map[position.y + 1][position.x] != SubTile::Closed {
-        neighbors.push(Position {
-            y: position.y + 1,
-            x: position.x,
-        });
-    }
-    if position.x > 0 && map[position.y][position.x - 1] != SubTile::Closed {
-        neighbors.push(Position {
-            y: position.y,
-            x: position.x - 1,
-        });
+// walking along a row just below the tile centers, we cross the loop at every
+// loop tile with a pipe leading down; tiles after an odd number of crossings
+// are enclosed
+fn count_enclosed_by_scanline(map: &[Vec<Tile>], cycle: &[Position]) -> usize {
+    let mut on_loop = map
+        .iter()
+        .map(|row| vec![false; row.len()])
+        .collect::<Vec<Vec<bool>>>();
+    for p in cycle {
+        on_loop[p.y][p.x] = true;
     }
-    if position.x + 1 < map[0].len() && map[position.y][position.x + 1] != SubTile::Closed {
-        neighbors.push(Position {
-            y: position.y,
-            x: position.x + 1,
-        });
+    let mut enclosed = 0;
+    for (row, on_loop) in map.iter().zip(on_loop) {
+        let mut inside = false;
+        for (tile, on_loop) in row.iter().zip(on_loop) {
+            if !on_loop {
+                enclosed += usize::from(inside);
+            } else if matches!(tile, Tile::Vertical | Tile::SW | Tile::SE) {
+                inside = !inside;
+            }
+        }
     }
-    neighbors
+    enclosed
 }
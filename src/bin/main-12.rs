@@ -145,7 +145,7 @@ fn count_arrangements(
     // if we reach here, we're still on the right track!
 
     // if there's nothing to replace, check validity
-    if !conditions.iter().any(|&c| c == Condition::Unknown) {
+    if !conditions.contains(&Condition::Unknown) {
         if compute_summary(conditions) == summary {
             return 1;
         } else {
@@ -187,8 +187,7 @@ fn count_arrangements(
 
 fn unfold_record(record: &Record) -> Record {
     Record {
-        conditions: iter::repeat(&record.conditions)
-            .take(5)
+        conditions: iter::repeat_n(&record.conditions, 5)
             .flat_map(|v| {
                 let mut v = v.to_vec();
                 v.insert(0, Condition::Unknown);
@@ -196,8 +195,7 @@ fn unfold_record(record: &Record) -> Record {
             })
             .skip(1) // we should only insert '?' *between* lists
             .collect::<Vec<Condition>>(),
-        summary: iter::repeat(&record.summary)
-            .take(5)
+        summary: iter::repeat_n(&record.summary, 5)
             .flatten()
             .copied()
             .collect::<Vec<usize>>(),
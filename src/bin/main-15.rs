@@ -1,4 +1,4 @@
-use std::{error::Error, fmt, fs};
+use std::fs;
 
 fn main() {
     let filename = "inputs/15.txt";
@@ -68,17 +68,6 @@ impl From<&str> for Instruction {
     }
 }
 
-#[derive(Debug)]
-struct ParseInstructionError;
-
-impl fmt::Display for ParseInstructionError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
-impl Error for ParseInstructionError {}
-
 #[derive(Clone, Copy, Debug)]
 enum Operation {
     Remove,
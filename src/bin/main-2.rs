@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs, num::ParseIntError, str::FromStr};
+use std::{collections::HashMap, env, error::Error, fmt, fs, str::FromStr};
 
 fn main() {
     let filename = "inputs/2.txt";
@@ -9,18 +9,30 @@ fn main() {
         .lines()
         .map(|l| {
             l.parse::<Game>()
-                .unwrap_or_else(|_| panic!("Could not convert line \"{l}\" to `Game`"))
+                .unwrap_or_else(|e| panic!("Could not convert line \"{l}\" to `Game`: {e}"))
         })
         .collect::<Vec<Game>>();
 
+    // optionally dump every single draw for analysis elsewhere instead of
+    // solving, e.g. `--csv > draws.csv`
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if let Some(arg) = args.first() {
+        match (arg.as_str(), args.len()) {
+            ("--csv", 1) => print!("{}", to_csv(&games)),
+            ("--json", 1) => println!("{}", to_json(&games)),
+            _ => panic!("Unknown arguments {args:?}, expected either `--csv` or `--json`"),
+        }
+        return;
+    }
+
     let max_count = HashMap::from([(Color::Red, 12), (Color::Green, 13), (Color::Blue, 14)]);
 
     let sum_of_ids = games
         .iter()
         .map(|g| {
             for r in g.rounds.iter() {
-                for (color, count) in r.counts.iter() {
-                    if *count > max_count[&color] {
+                for (color, count) in r.draws.iter() {
+                    if *count > max_count[color] {
                         return 0;
                     }
                 }
@@ -36,7 +48,7 @@ fn main() {
             let mut min_count =
                 HashMap::from([(Color::Red, 0), (Color::Green, 0), (Color::Blue, 0)]);
             for r in g.rounds.iter() {
-                for (color, count) in r.counts.iter() {
+                for (color, count) in r.draws.iter() {
                     min_count.insert(*color, std::cmp::max(min_count[color], *count));
                 }
             }
@@ -57,56 +69,118 @@ impl FromStr for Game {
 
     fn from_str(s: &str) -> Result<Self, ParseGameError> {
         fn parse_id(s: &str) -> Result<usize, ParseGameError> {
-            let mut s = s.split(' ');
-            assert!(s.next().ok_or(ParseGameError)? == "Game");
-            Ok(s.next().ok_or(ParseGameError)?.parse::<usize>()?)
+            let id = s
+                .strip_prefix("Game ")
+                .ok_or_else(|| ParseGameError::InvalidHeader(s.to_string()))?;
+            id.parse::<usize>()
+                .map_err(|_| ParseGameError::InvalidHeader(s.to_string()))
         }
 
-        fn parse_rounds(s: &str) -> Result<Vec<Round>, ParseGameError> {
+        fn parse_rounds(s: &str, game: usize) -> Result<Vec<Round>, ParseGameError> {
             let mut rounds = Vec::new();
-            for round_record in s.split("; ") {
-                let mut round = Round::new();
+            for (i, round_record) in s.split("; ").enumerate() {
+                let round = i + 1;
+                let mut draws = Vec::new();
                 for count_record in round_record.split(", ") {
-                    let count_record = count_record
-                        .split(' ')
-                        .skip_while(|&x| x.is_empty())
-                        .collect::<Vec<&str>>();
-                    assert!(count_record.len() == 2);
-                    let count = count_record[0].parse::<usize>()?;
-                    let color = count_record[1].parse::<Color>()?;
-                    round.counts.insert(color, count);
+                    let token = count_record.trim();
+                    let invalid = |kind| ParseGameError::InvalidDraw {
+                        game,
+                        round,
+                        token: token.to_string(),
+                        kind,
+                    };
+                    let (count, color) = token
+                        .split_once(' ')
+                        .ok_or_else(|| invalid(DrawErrorKind::Malformed))?;
+                    let count = count
+                        .parse::<usize>()
+                        .map_err(|_| invalid(DrawErrorKind::Count))?;
+                    let color = color
+                        .parse::<Color>()
+                        .map_err(|_| invalid(DrawErrorKind::Color))?;
+                    draws.push((color, count));
                 }
-                rounds.push(round);
+                rounds.push(Round { draws });
             }
             Ok(rounds)
         }
 
-        let mut s = s.split(':');
-        let id = parse_id(s.next().ok_or(ParseGameError)?)?;
-        let rounds = parse_rounds(s.next().ok_or(ParseGameError)?)?;
+        let (id, rounds) = s.split_once(':').ok_or(ParseGameError::MissingSeparator)?;
+        let id = parse_id(id)?;
+        let rounds = parse_rounds(rounds, id)?;
         Ok(Game { id, rounds })
     }
 }
 
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Game {}:", self.id)?;
+        for (i, r) in self.rounds.iter().enumerate() {
+            let separator = if i == 0 { " " } else { "; " };
+            write!(f, "{separator}{r}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
-struct ParseGameError;
+enum ParseGameError {
+    MissingSeparator,
+    InvalidHeader(String),
+    InvalidDraw {
+        game: usize,
+        round: usize,
+        token: String,
+        kind: DrawErrorKind,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DrawErrorKind {
+    Malformed,
+    Count,
+    Color,
+}
 
-impl From<ParseIntError> for ParseGameError {
-    fn from(_value: ParseIntError) -> Self {
-        Self
+impl fmt::Display for ParseGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeparator => write!(f, "missing ':' after the game header"),
+            Self::InvalidHeader(header) => {
+                write!(f, "expected \"Game <id>\" but found \"{header}\"")
+            }
+            Self::InvalidDraw {
+                game,
+                round,
+                token,
+                kind,
+            } => {
+                let reason = match kind {
+                    DrawErrorKind::Malformed => "expected \"<count> <color>\"",
+                    DrawErrorKind::Count => "count is not a non-negative integer",
+                    DrawErrorKind::Color => "color is not one of red, green or blue",
+                };
+                write!(f, "game {game}, round {round}: \"{token}\": {reason}")
+            }
+        }
     }
 }
 
+impl Error for ParseGameError {}
+
 #[derive(Debug)]
 struct Round {
-    pub counts: std::collections::HashMap<Color, usize>,
+    // keep the order of the record so we can reproduce it
+    pub draws: Vec<(Color, usize)>,
 }
 
-impl Round {
-    fn new() -> Self {
-        Self {
-            counts: HashMap::from([(Color::Red, 0), (Color::Green, 0), (Color::Blue, 0)]),
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (color, count)) in self.draws.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            write!(f, "{separator}{count} {color}")?;
         }
+        Ok(())
     }
 }
 
@@ -118,7 +192,7 @@ enum Color {
 }
 
 impl FromStr for Color {
-    type Err = ParseGameError;
+    type Err = ParseColorError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "red" => Ok(Color::Red),
@@ -128,3 +202,47 @@ impl FromStr for Color {
         }
     }
 }
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Blue => "blue",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ParseColorError;
+
+fn to_csv(games: &[Game]) -> String {
+    let mut csv = String::from("game,round,color,count\n");
+    for g in games {
+        for (i, r) in g.rounds.iter().enumerate() {
+            for (color, count) in r.draws.iter() {
+                csv.push_str(&format!("{},{},{color},{count}\n", g.id, i + 1));
+            }
+        }
+    }
+    csv
+}
+
+fn to_json(games: &[Game]) -> String {
+    let draws = games
+        .iter()
+        .flat_map(|g| {
+            g.rounds.iter().enumerate().flat_map(move |(i, r)| {
+                r.draws.iter().map(move |(color, count)| {
+                    format!(
+                        "{{\"game\":{},\"round\":{},\"color\":\"{color}\",\"count\":{count}}}",
+                        g.id,
+                        i + 1
+                    )
+                })
+            })
+        })
+        .collect::<Vec<String>>();
+    format!("[{}]", draws.join(","))
+}
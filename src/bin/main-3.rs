@@ -1,124 +1,329 @@
-use std::fs;
+use std::{collections::HashMap, env, fs};
+
+const RESET: &str = "\x1b[0m";
 
 fn main() {
     let filename = "inputs/3.txt";
     let content =
         fs::read_to_string(filename).expect("Should have been able to read the input file");
 
-    let data = content
-        .lines()
-        .map(|l| l.chars().map(Element::from).collect::<Vec<Element>>())
-        .collect::<Vec<Vec<Element>>>();
-    let numbers = extract_numbers(data);
-    let sum = numbers
-        .iter()
-        .filter(|n| {
-            n.neighbors
-                .iter()
-                .any(|e| *e == Element::Symbol || *e == Element::Gear)
-        })
-        .map(|n| n.value)
-        .sum::<usize>();
-    println!("The sum of all of the part numbers in the engine schematic is {sum}.");
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let config = Config {
+        signed: args.iter().any(|a| a == "--signed"),
+    };
+    let schematic = Schematic::parse(&content, config);
 
-    let mut prod = 0;
-    'outer: for (i, ni) in numbers.iter().enumerate() {
-        for nj in numbers.iter().skip(i + 1) {
-            for gi in ni.gears.iter() {
-                for gj in nj.gears.iter() {
-                    if gi.position == gj.position {
-                        prod += ni.value * nj.value;
-                        continue 'outer;
-                    }
-                }
-            }
-        }
+    // show the schematic instead of solving, either highlighted for the
+    // terminal or as an html page
+    if args.iter().any(|a| a == "--render") && args.iter().any(|a| a == "--html") {
+        panic!("Expected either `--render` or `--html`, not both");
+    }
+    if args.iter().any(|a| a == "--render") {
+        print!("{}", schematic.render_ansi());
+        return;
+    }
+    if args.iter().any(|a| a == "--html") {
+        print!("{}", schematic.render_html());
+        return;
     }
+
+    let total = schematic.numbers.iter().map(|n| n.value).sum::<i64>();
+    let sum = total
+        - schematic
+            .unattached_numbers()
+            .iter()
+            .map(|n| n.value)
+            .sum::<i64>();
+    println!("The sum of all of the part numbers in the engine schematic is {sum}.");
+
+    let prod = schematic
+        .symbols_with('*', 2)
+        .iter()
+        .map(|s| schematic.neighbor_values(s.position).product::<i64>())
+        .sum::<i64>();
     println!("The sum of all of the gear ratios in your engine schematic is {prod}.");
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Element {
-    Digit(usize),
-    Period,
-    Gear,
-    Symbol,
+#[derive(Clone, Copy, Debug)]
+struct Config {
+    // treat a '-' directly in front of a digit as the sign of the number
+    // instead of as a symbol
+    signed: bool,
+}
+
+#[derive(Debug)]
+struct Schematic {
+    numbers: Vec<NumberSpan>,
+    symbols: HashMap<(usize, usize), char>,
+    // indices into `numbers` of all numbers adjacent to each symbol
+    neighbors: HashMap<(usize, usize), Vec<usize>>,
+    grid: Vec<Vec<char>>,
+    height: usize,
+    width: usize,
 }
 
-impl From<char> for Element {
-    fn from(value: char) -> Self {
-        if let Some(v) = value.to_digit(10) {
-            Element::Digit(v as usize)
-        } else if value == '.' {
-            Element::Period
-        } else if value == '*' {
-            Element::Gear
-        } else {
-            Element::Symbol
+impl Schematic {
+    fn parse(s: &str, config: Config) -> Self {
+        let mut numbers = Vec::new();
+        let mut symbols = HashMap::new();
+        let mut width = 0;
+        let mut height = 0;
+        for (row, l) in s.lines().enumerate() {
+            let (row_numbers, row_symbols) = tokenize_row(row, l, config);
+            numbers.extend(row_numbers);
+            symbols.extend(row_symbols.into_iter().map(|s| (s.position, s.c)));
+            width = std::cmp::max(width, l.chars().count());
+            height = row + 1;
         }
+        let grid = s
+            .lines()
+            .map(|l| l.chars().collect::<Vec<char>>())
+            .collect::<Vec<Vec<char>>>();
+        let mut schematic = Self {
+            numbers,
+            symbols,
+            neighbors: HashMap::new(),
+            grid,
+            height,
+            width,
+        };
+        schematic.index_neighbors();
+        schematic
     }
-}
 
-fn extract_numbers(data: Vec<Vec<Element>>) -> Vec<Number> {
-    let mut numbers = Vec::new();
-    let mut stack = Vec::new();
-    let mut neighbors = Vec::new();
-    let mut gears = Vec::new();
-    for i in 0..data.len() {
-        for j in 0..data[0].len() {
-            match data[i][j] {
-                Element::Digit(v) => {
-                    stack.push(v);
-                    for di in [-1, 0, 1] {
-                        for dj in [-1, 0, 1] {
-                            let k = i as i32 + di;
-                            let l = j as i32 + dj;
-                            if k >= 0 && k < data.len() as i32 && l >= 0 && l < data[0].len() as i32
-                            {
-                                let k = k as usize;
-                                let l = l as usize;
-                                let n = data[k][l];
-                                neighbors.push(n);
-                                if n == Element::Gear {
-                                    gears.push(Gear { position: (k, l) });
-                                }
-                            }
-                        }
-                    }
+    fn index_neighbors(&mut self) {
+        let mut neighbors = self
+            .symbols
+            .keys()
+            .map(|&p| (p, Vec::new()))
+            .collect::<HashMap<(usize, usize), Vec<usize>>>();
+        for (i, n) in self.numbers.iter().enumerate() {
+            for s in self.adjacent_symbols(n) {
+                neighbors.get_mut(&s.position).unwrap().push(i);
+            }
+        }
+        self.neighbors = neighbors;
+    }
+
+    fn adjacent_numbers(&self, position: (usize, usize)) -> impl Iterator<Item = &NumberSpan> {
+        self.neighbors
+            .get(&position)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.numbers[i])
+    }
+
+    fn neighbor_values(&self, position: (usize, usize)) -> impl Iterator<Item = i64> + '_ {
+        self.adjacent_numbers(position).map(|n| n.value)
+    }
+
+    // all symbols `c` with exactly `k` adjacent numbers, in reading order
+    fn symbols_with(&self, c: char, k: usize) -> Vec<Symbol> {
+        let mut symbols = self
+            .symbols
+            .iter()
+            .filter(|&(p, &sc)| sc == c && self.neighbors[p].len() == k)
+            .map(|(&position, &c)| Symbol { position, c })
+            .collect::<Vec<Symbol>>();
+        symbols.sort_unstable_by_key(|s| s.position);
+        symbols
+    }
+
+    fn unattached_numbers(&self) -> Vec<&NumberSpan> {
+        let mut attached = vec![false; self.numbers.len()];
+        for &i in self.neighbors.values().flatten() {
+            attached[i] = true;
+        }
+        self.numbers
+            .iter()
+            .zip(attached)
+            .filter(|(_, a)| !a)
+            .map(|(n, _)| n)
+            .collect::<Vec<&NumberSpan>>()
+    }
+
+    fn highlights(&self) -> Vec<Vec<Highlight>> {
+        let mut highlights = self
+            .grid
+            .iter()
+            .map(|row| vec![Highlight::Plain; row.len()])
+            .collect::<Vec<Vec<Highlight>>>();
+        let unattached = self.unattached_numbers();
+        for n in self.numbers.iter() {
+            let highlight = if unattached.contains(&n) {
+                Highlight::NonPartNumber
+            } else {
+                Highlight::PartNumber
+            };
+            for h in highlights[n.row][n.start..n.end].iter_mut() {
+                *h = highlight;
+            }
+        }
+        for s in self.symbols_with('*', 2) {
+            highlights[s.position.0][s.position.1] = Highlight::Gear;
+        }
+        highlights
+    }
+
+    fn render_ansi(&self) -> String {
+        let mut out = String::new();
+        for (row, highlights) in self.grid.iter().zip(self.highlights()) {
+            let mut current = Highlight::Plain;
+            for (&c, h) in row.iter().zip(highlights) {
+                if h != current {
+                    out.push_str(RESET);
+                    out.push_str(h.ansi_color());
+                    current = h;
                 }
-                Element::Period | Element::Symbol | Element::Gear => {
-                    if stack.is_empty() {
-                        continue;
+                out.push(c);
+            }
+            out.push_str(RESET);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn render_html(&self) -> String {
+        let mut out = String::from(
+            "<style>.part{color:green}.non-part{color:red}.gear{color:orange;font-weight:bold}</style>\n<pre>\n",
+        );
+        for (i, (row, highlights)) in self.grid.iter().zip(self.highlights()).enumerate() {
+            let mut current = Highlight::Plain;
+            for (j, (&c, h)) in row.iter().zip(highlights).enumerate() {
+                if h != current || h == Highlight::Gear {
+                    if current != Highlight::Plain {
+                        out.push_str("</span>");
                     }
-                    let mut value = 0;
-                    let mut base = 1;
-                    while let Some(d) = stack.pop() {
-                        value += d * base;
-                        base *= 10;
+                    match h {
+                        Highlight::Plain => {}
+                        Highlight::Gear => {
+                            let values = self.neighbor_values((i, j)).collect::<Vec<i64>>();
+                            let ratio = values.iter().product::<i64>();
+                            out.push_str(&format!(
+                                "<span class=\"gear\" title=\"{} * {} = {ratio}\">",
+                                values[0], values[1]
+                            ));
+                        }
+                        _ => out.push_str(&format!("<span class=\"{}\">", h.css_class())),
                     }
-                    numbers.push(Number {
-                        value,
-                        neighbors: neighbors.clone(),
-                        gears: gears.clone(),
+                    current = h;
+                }
+                match c {
+                    '&' => out.push_str("&amp;"),
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    _ => out.push(c),
+                }
+            }
+            if current != Highlight::Plain {
+                out.push_str("</span>");
+            }
+            out.push('\n');
+        }
+        out.push_str("</pre>\n");
+        out
+    }
+
+    fn adjacent_symbols(&self, number: &NumberSpan) -> Vec<Symbol> {
+        let mut adjacent = Vec::new();
+        let first_row = number.row.saturating_sub(1);
+        let last_row = std::cmp::min(number.row + 1, self.height.saturating_sub(1));
+        let first_col = number.start.saturating_sub(1);
+        let last_col = std::cmp::min(number.end, self.width.saturating_sub(1));
+        for i in first_row..=last_row {
+            for j in first_col..=last_col {
+                if let Some(&c) = self.symbols.get(&(i, j)) {
+                    adjacent.push(Symbol {
+                        position: (i, j),
+                        c,
                     });
-                    stack.clear();
-                    neighbors.clear();
-                    gears.clear();
                 }
             }
         }
+        adjacent
     }
-    numbers
 }
 
-#[derive(Debug)]
-struct Number {
-    value: usize,
-    neighbors: Vec<Element>,
-    gears: Vec<Gear>,
+// split a single row into number spans and symbol cells; spans never cross
+// the end of the row
+fn tokenize_row(row: usize, l: &str, config: Config) -> (Vec<NumberSpan>, Vec<Symbol>) {
+    let chars = l.chars().collect::<Vec<char>>();
+    let mut numbers = Vec::new();
+    let mut symbols = Vec::new();
+    let mut j = 0;
+    while j < chars.len() {
+        let c = chars[j];
+        let is_sign =
+            config.signed && c == '-' && chars.get(j + 1).is_some_and(|c| c.is_ascii_digit());
+        if c.is_ascii_digit() || is_sign {
+            let start = j;
+            j += 1;
+            while j < chars.len() && chars[j].is_ascii_digit() {
+                j += 1;
+            }
+            let value = chars[start..j]
+                .iter()
+                .collect::<String>()
+                .parse::<i64>()
+                .expect("Should have been able to parse a run of digits");
+            numbers.push(NumberSpan {
+                row,
+                start,
+                end: j,
+                value,
+            });
+            continue;
+        }
+        if c != '.' {
+            symbols.push(Symbol {
+                position: (row, j),
+                c,
+            });
+        }
+        j += 1;
+    }
+    (numbers, symbols)
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Gear {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NumberSpan {
+    row: usize,
+    // columns `start..end` of `row`, including the sign if any
+    start: usize,
+    end: usize,
+    value: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Symbol {
     position: (usize, usize),
+    c: char,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Highlight {
+    Plain,
+    PartNumber,
+    NonPartNumber,
+    Gear,
+}
+
+impl Highlight {
+    fn ansi_color(&self) -> &'static str {
+        match self {
+            Self::Plain => "",
+            Self::PartNumber => "\x1b[32m",
+            Self::NonPartNumber => "\x1b[31m",
+            Self::Gear => "\x1b[1;33m",
+        }
+    }
+
+    fn css_class(&self) -> &'static str {
+        match self {
+            Self::Plain => "",
+            Self::PartNumber => "part",
+            Self::NonPartNumber => "non-part",
+            Self::Gear => "gear",
+        }
+    }
 }
@@ -1,15 +1,19 @@
-use std::{fs, num::ParseIntError, str::FromStr};
+use std::{collections::HashSet, env, error::Error, fmt, fs, str::FromStr};
 
 fn main() {
     let filename = "inputs/4.txt";
     let content =
         fs::read_to_string(filename).expect("Should have been able to read the input file");
 
-    let worth = content
-        .lines()
-        .map(|l| {
-            let (winning_numbers, numbers) = parse_line(l);
-            let matches = count_matches(winning_numbers, numbers);
+    let cards = parse_cards(&content).unwrap_or_else(|e| panic!("Could not parse cards: {e}"));
+    let matches = cards
+        .iter()
+        .map(Card::count_matches)
+        .collect::<Vec<usize>>();
+
+    let worth = matches
+        .iter()
+        .map(|&matches| {
             if matches == 0 {
                 0
             } else {
@@ -20,54 +24,170 @@ fn main() {
 
     println!("The colorful cards are {worth} worth in total.");
 
-    let originals = content
-        .lines()
-        .map(parse_line)
-        .enumerate()
-        .collect::<Vec<(usize, (Numbers, Numbers))>>();
-    let mut count = originals.len();
-    let mut stack = originals.clone();
-    while let Some((offset, (winning_numbers, numbers))) = stack.pop() {
-        let matches = count_matches(winning_numbers, numbers);
-        for i in 0..matches {
-            stack.push(originals[offset + 1 + i].clone());
-            count += 1;
+    let copies = count_copies(&matches).unwrap_or_else(|e| panic!("{e}"));
+    let count = copies.iter().sum::<usize>();
+    println!("In total you end up with {count} scratchcards.");
+
+    if env::args().skip(1).any(|a| a == "--copies") {
+        for (card, c) in cards.iter().zip(copies.iter()) {
+            println!("Card {}: {c}", card.id);
         }
     }
-    println!("In total you end up with {count} scratchcards.");
+}
+
+#[derive(Clone, Debug)]
+struct Card {
+    id: usize,
+    winning_numbers: Numbers,
+    numbers: Numbers,
+}
+
+impl Card {
+    fn count_matches(&self) -> usize {
+        self.numbers
+            .numbers
+            .intersection(&self.winning_numbers.numbers)
+            .count()
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, numbers) = s.split_once(':').ok_or(ParseCardError::MissingColon)?;
+        let id = header
+            .strip_prefix("Card")
+            .and_then(|id| id.trim().parse::<usize>().ok())
+            .ok_or_else(|| ParseCardError::InvalidHeader(header.to_string()))?;
+        let (winning_numbers, numbers) =
+            numbers.split_once('|').ok_or(ParseCardError::MissingBar)?;
+        Ok(Self {
+            id,
+            winning_numbers: winning_numbers.parse::<Numbers>()?,
+            numbers: numbers.parse::<Numbers>()?,
+        })
+    }
 }
 
 #[derive(Clone, Debug)]
 struct Numbers {
-    numbers: Vec<usize>,
+    numbers: HashSet<usize>,
 }
 
 impl FromStr for Numbers {
-    type Err = ParseIntError;
+    type Err = ParseCardError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let numbers = s
-            .split(' ')
-            .filter(|si| !si.is_empty())
-            .map(|si| si.parse::<usize>().unwrap())
-            .collect::<Vec<usize>>();
+        let mut numbers = HashSet::new();
+        for si in s.split(' ').filter(|si| !si.is_empty()) {
+            let n = si
+                .parse::<usize>()
+                .map_err(|_| ParseCardError::InvalidNumber(si.to_string()))?;
+            if !numbers.insert(n) {
+                return Err(ParseCardError::DuplicateNumber(n));
+            }
+        }
         Ok(Self { numbers })
     }
 }
 
-fn parse_line(l: &str) -> (Numbers, Numbers) {
-    let v = l.split(':').collect::<Vec<&str>>();
-    let v = v[1].split('|').collect::<Vec<&str>>();
-    (
-        v[0].parse::<Numbers>().unwrap(),
-        v[1].parse::<Numbers>().unwrap(),
-    )
+#[derive(Debug, PartialEq, Eq)]
+enum ParseCardError {
+    MissingColon,
+    MissingBar,
+    InvalidHeader(String),
+    InvalidNumber(String),
+    DuplicateNumber(usize),
+    UnexpectedId { expected: usize, found: usize },
 }
 
-fn count_matches(winning_numbers: Numbers, numbers: Numbers) -> usize {
-    numbers
-        .numbers
-        .iter()
-        .filter(|&&n| winning_numbers.numbers.iter().any(|&wn| wn == n))
-        .count()
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingColon => write!(f, "missing ':' after the card header"),
+            Self::MissingBar => write!(f, "missing '|' between winning and held numbers"),
+            Self::InvalidHeader(header) => {
+                write!(f, "expected \"Card <id>\" but found \"{header}\"")
+            }
+            Self::InvalidNumber(token) => write!(f, "\"{token}\" is not a number"),
+            Self::DuplicateNumber(n) => write!(f, "{n} appears twice on the same side"),
+            Self::UnexpectedId { expected, found } => {
+                write!(f, "expected card {expected} but found card {found}")
+            }
+        }
+    }
+}
+
+impl Error for ParseCardError {}
+
+fn parse_cards(s: &str) -> Result<Vec<Card>, ParseCardsError> {
+    s.lines()
+        .enumerate()
+        .map(|(i, l)| {
+            let card = l.parse::<Card>().and_then(|card| {
+                if card.id == i + 1 {
+                    Ok(card)
+                } else {
+                    Err(ParseCardError::UnexpectedId {
+                        expected: i + 1,
+                        found: card.id,
+                    })
+                }
+            });
+            card.map_err(|error| ParseCardsError { line: i + 1, error })
+        })
+        .collect::<Result<Vec<Card>, ParseCardsError>>()
+}
+
+#[derive(Debug)]
+struct ParseCardsError {
+    line: usize,
+    error: ParseCardError,
+}
+
+impl fmt::Display for ParseCardsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
 }
+
+impl Error for ParseCardsError {}
+
+// number of instances of each card after all wins have been cashed in; every
+// card only ever wins copies of later cards, so a single forward pass suffices
+fn count_copies(matches: &[usize]) -> Result<Vec<usize>, CascadeError> {
+    let mut copies = vec![1; matches.len()];
+    for (i, &m) in matches.iter().enumerate() {
+        if i + m >= matches.len() {
+            return Err(CascadeError {
+                card: i + 1,
+                last_won: i + 1 + m,
+                table_size: matches.len(),
+            });
+        }
+        for j in i + 1..i + 1 + m {
+            copies[j] += copies[i];
+        }
+    }
+    Ok(copies)
+}
+
+#[derive(Debug)]
+struct CascadeError {
+    card: usize,
+    last_won: usize,
+    table_size: usize,
+}
+
+impl fmt::Display for CascadeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "card {} wins copies up to card {}, but there are only {} cards",
+            self.card, self.last_won, self.table_size
+        )
+    }
+}
+
+impl Error for CascadeError {}
@@ -1,90 +1,40 @@
-use std::fs;
+use std::{env, error::Error, fmt, fs, ops::Range, str::FromStr};
 
 fn main() {
     let filename = "inputs/5.txt";
     let content =
         fs::read_to_string(filename).expect("Should have been able to read the input file");
 
-    let mut lines = content.lines();
+    let almanac = content
+        .parse::<Almanac>()
+        .unwrap_or_else(|e| panic!("Could not parse almanac: {e}"));
 
-    let seeds = parse_seeds(lines.next().unwrap());
-
-    // skip to next heading, pop it off, then parse until next empty line; rinse
-    // and repeat
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let seed_to_soil_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
-
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let soil_to_fertilizer_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
-
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let fertilizer_to_water_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
-
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let water_to_light_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
-
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let light_to_temperature_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
-
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let temperature_to_humidity_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
+    // overlapping and overflowing ranges are only fatal in strict mode
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let issues = almanac.validate();
+    for issue in issues.iter() {
+        eprintln!("Warning: {issue}");
+    }
+    if args.iter().any(|a| a == "--strict") && !issues.is_empty() {
+        panic!("Rejecting almanac with {} invalid ranges", issues.len());
+    }
 
-    let mut lines = lines.skip_while(|l| l.find(':').is_none());
-    lines.next();
-    let humidity_to_location_map = parse_range_map(
-        lines
-            .clone()
-            .take_while(|l| !l.is_empty())
-            .collect::<Vec<&str>>(),
-    );
+    let chain = almanac
+        .chain("seed", "location")
+        .unwrap_or_else(|e| panic!("{e}"));
 
-    let lln = seeds
+    // collapse all maps into a single table
+    let location_map = chain
         .iter()
-        .map(|&idx| {
-            let idx = seed_to_soil_map.convert(idx);
-            let idx = soil_to_fertilizer_map.convert(idx);
-            let idx = fertilizer_to_water_map.convert(idx);
-            let idx = water_to_light_map.convert(idx);
-            let idx = light_to_temperature_map.convert(idx);
-            let idx = temperature_to_humidity_map.convert(idx);
-            humidity_to_location_map.convert(idx)
+        .try_fold(SortedRangeMap::identity(), |acc, map| {
+            Ok::<SortedRangeMap, OverflowError>(acc.compose(&map.to_sorted()?))
         })
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    let lln = almanac
+        .seeds
+        .iter()
+        .map(|&idx| location_map.convert(idx))
         .min()
         .unwrap();
 
@@ -92,76 +42,497 @@ fn main() {
         "The lowest location number that corresponds to any of the initial seed numbers is {lln}."
     );
 
-    // brute force part 2
-    let lln = seeds
+    // push whole intervals through the map instead of individual seeds
+    let seed_ranges = almanac
+        .seeds
         .as_slice()
         .chunks(2)
         .map(|e| {
-            let start = e[0];
-            let length = e[1];
-            // TODO speed this up by choosing idx better
-            (start..start + length)
-                .map(|idx| {
-                    let idx = seed_to_soil_map.convert(idx);
-                    let idx = soil_to_fertilizer_map.convert(idx);
-                    let idx = fertilizer_to_water_map.convert(idx);
-                    let idx = water_to_light_map.convert(idx);
-                    let idx = light_to_temperature_map.convert(idx);
-                    let idx = temperature_to_humidity_map.convert(idx);
-                    humidity_to_location_map.convert(idx)
-                })
-                .min()
-                .unwrap()
+            e[0]..e[0]
+                .checked_add(e[1])
+                .unwrap_or_else(|| panic!("Seed range {} {} overflows", e[0], e[1]))
         })
+        .collect::<Vec<Range<usize>>>();
+    let lln = seed_ranges
+        .iter()
+        .flat_map(|r| location_map.convert_range(r.clone()))
+        .map(|r| r.start)
         .min()
         .unwrap();
     println!(
         "The lowest location number that corresponds to any of the initial seed numbers is {lln}."
     );
+
+    // convert a single value between any two connected categories, e.g.
+    // `--convert soil humidity 81`
+    if let Some(i) = args.iter().position(|a| a == "--convert") {
+        let (from, to, value) = match &args[i + 1..] {
+            [from, to, value, ..] => (from, to, value),
+            _ => panic!("Expected `--convert <from> <to> <value>`"),
+        };
+        let value = value
+            .parse::<usize>()
+            .unwrap_or_else(|_| panic!("Could not convert \"{value}\" to `usize`"));
+        let chain = almanac.chain(from, to).unwrap_or_else(|e| panic!("{e}"));
+        let converted = chain
+            .iter()
+            .try_fold(value, |idx, map| map.convert(idx))
+            .unwrap_or_else(|e| panic!("{e}"));
+        println!("{from} {value} corresponds to {to} {converted}.");
+    }
+
+    // count the seeds of all seed ranges which end up at location <= n, e.g.
+    // `--seeds-below 50`
+    if let Some(i) = args.iter().position(|a| a == "--seeds-below") {
+        let n = args
+            .get(i + 1)
+            .and_then(|n| n.parse::<usize>().ok())
+            .expect("Expected `--seeds-below <location>`");
+        let locations = 0..n.saturating_add(1);
+        // if every location belongs to exactly one seed, look up the seeds of
+        // all small locations at once; otherwise push the seed ranges forward
+        // and count the seeds that land in `locations`
+        let count = match location_map.invert() {
+            Ok(inverse) => {
+                let candidates = inverse.convert_range(locations);
+                seed_ranges
+                    .iter()
+                    .flat_map(|s| candidates.iter().map(move |c| overlap(s, c)))
+                    .sum::<usize>()
+            }
+            Err(_) => seed_ranges
+                .iter()
+                .flat_map(|s| location_map.convert_range(s.clone()))
+                .map(|l| overlap(&l, &locations))
+                .sum::<usize>(),
+        };
+        println!("{count} of the initial seed numbers end up at a location of at most {n}.");
+    }
 }
 
-fn parse_seeds(l: &str) -> Vec<usize> {
-    let l = l.split(':').collect::<Vec<&str>>();
-    l[1].split(' ')
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect::<Vec<usize>>()
+// number of values in both ranges
+fn overlap(a: &Range<usize>, b: &Range<usize>) -> usize {
+    std::cmp::min(a.end, b.end).saturating_sub(std::cmp::max(a.start, b.start))
 }
 
-fn parse_range_map(lines: Vec<&str>) -> MultiRangeMap {
-    let range_maps = lines
-        .iter()
-        .map(|l| {
-            let l = l
-                .split(' ')
-                .map(|s| s.parse::<usize>().unwrap())
-                .collect::<Vec<usize>>();
-            RangeMap {
-                destination_start: l[0],
-                source_start: l[1],
-                range_length: l[2],
+#[derive(Debug)]
+struct Almanac {
+    seeds: Vec<usize>,
+    maps: Vec<CategoryMap>,
+}
+
+#[derive(Debug)]
+struct CategoryMap {
+    source: String,
+    destination: String,
+    map: MultiRangeMap,
+}
+
+impl FromStr for Almanac {
+    type Err = AlmanacError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // keep track of (1-based) line numbers for error messages
+        let mut lines = s.lines().enumerate().map(|(i, l)| (i + 1, l));
+        let seeds = lines
+            .next()
+            .and_then(|(_, l)| l.strip_prefix("seeds:"))
+            .ok_or(AlmanacError::MissingSeeds)?;
+        let seeds = parse_numbers(1, seeds)?;
+
+        // every `X-to-Y map:` heading starts a new section which runs until the
+        // next empty line
+        let mut maps = Vec::new();
+        let mut lines = lines.peekable();
+        while let Some((line, l)) = lines.next() {
+            if l.is_empty() {
+                continue;
+            }
+            let (source, destination) = l
+                .strip_suffix(" map:")
+                .and_then(|h| h.split_once("-to-"))
+                .ok_or_else(|| AlmanacError::InvalidHeading(line, l.to_string()))?;
+            let mut section = Vec::new();
+            while let Some(l) = lines.next_if(|(_, l)| !l.is_empty()) {
+                section.push(l);
             }
+            maps.push(CategoryMap {
+                source: source.to_string(),
+                destination: destination.to_string(),
+                map: parse_range_map(section)?,
+            });
+        }
+        Ok(Self { seeds, maps })
+    }
+}
+
+impl Almanac {
+    fn validate(&self) -> Vec<RangeIssue> {
+        self.maps
+            .iter()
+            .flat_map(|m| {
+                let name = format!("{}-to-{}", m.source, m.destination);
+                m.map.validate().into_iter().map(move |kind| RangeIssue {
+                    map: name.clone(),
+                    kind,
+                })
+            })
+            .collect::<Vec<RangeIssue>>()
+    }
+
+    // the maps leading from category `from` to category `to`; there has to be
+    // exactly one way to get there
+    fn chain(&self, from: &str, to: &str) -> Result<Vec<&MultiRangeMap>, AlmanacError> {
+        let mut paths = Vec::new();
+        let mut stack = vec![(from, Vec::new())];
+        while let Some((category, path)) = stack.pop() {
+            if category == to {
+                paths.push(path);
+                continue;
+            }
+            for (i, m) in self.maps.iter().enumerate() {
+                // skip edges we already took to avoid running in circles
+                if m.source == category && !path.contains(&i) {
+                    let mut path = path.clone();
+                    path.push(i);
+                    stack.push((m.destination.as_str(), path));
+                }
+            }
+        }
+        match paths.len() {
+            0 => Err(AlmanacError::MissingChain {
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            1 => Ok(paths[0].iter().map(|&i| &self.maps[i].map).collect()),
+            _ => Err(AlmanacError::AmbiguousChain {
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum AlmanacError {
+    MissingSeeds,
+    InvalidHeading(usize, String),
+    InvalidNumber(usize, String),
+    InvalidRange(usize),
+    MissingChain { from: String, to: String },
+    AmbiguousChain { from: String, to: String },
+}
+
+impl fmt::Display for AlmanacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSeeds => write!(f, "the first line has to list the seeds"),
+            Self::InvalidHeading(line, l) => write!(
+                f,
+                "line {line}: expected \"<source>-to-<destination> map:\" but found \"{l}\""
+            ),
+            Self::InvalidNumber(line, token) => {
+                write!(f, "line {line}: \"{token}\" is not a number")
+            }
+            Self::InvalidRange(line) => write!(
+                f,
+                "line {line}: expected \"<destination start> <source start> <length>\""
+            ),
+            Self::MissingChain { from, to } => {
+                write!(f, "there is no chain of maps from {from} to {to}")
+            }
+            Self::AmbiguousChain { from, to } => {
+                write!(
+                    f,
+                    "there is more than one chain of maps from {from} to {to}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for AlmanacError {}
+
+#[derive(Debug, PartialEq, Eq)]
+struct RangeIssue {
+    map: String,
+    kind: RangeIssueKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RangeIssueKind {
+    Overlap { first: usize, second: usize },
+    Overflow { line: usize },
+}
+
+impl fmt::Display for RangeIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            RangeIssueKind::Overlap { first, second } => write!(
+                f,
+                "{} map: source ranges on lines {first} and {second} overlap",
+                self.map
+            ),
+            RangeIssueKind::Overflow { line } => write!(
+                f,
+                "{} map: range on line {line} does not fit into `usize`",
+                self.map
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OverflowError {
+    line: usize,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "range on line {} overflows `usize`", self.line)
+    }
+}
+
+impl Error for OverflowError {}
+
+fn parse_numbers(line: usize, s: &str) -> Result<Vec<usize>, AlmanacError> {
+    s.split(' ')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<usize>()
+                .map_err(|_| AlmanacError::InvalidNumber(line, s.to_string()))
         })
-        .collect::<Vec<RangeMap>>();
-    MultiRangeMap { range_maps }
+        .collect::<Result<Vec<usize>, AlmanacError>>()
+}
+
+fn parse_range_map(lines: Vec<(usize, &str)>) -> Result<MultiRangeMap, AlmanacError> {
+    let mut range_maps = Vec::new();
+    for &(line, l) in lines.iter() {
+        let l = parse_numbers(line, l)?;
+        if l.len() != 3 {
+            return Err(AlmanacError::InvalidRange(line));
+        }
+        range_maps.push(RangeMap {
+            destination_start: l[0],
+            source_start: l[1],
+            range_length: l[2],
+        });
+    }
+    Ok(MultiRangeMap {
+        range_maps,
+        lines: lines.iter().map(|&(line, _)| line).collect::<Vec<usize>>(),
+    })
 }
 
 #[derive(Debug)]
 struct MultiRangeMap {
     range_maps: Vec<RangeMap>,
+    // line of the input each range map was parsed from
+    lines: Vec<usize>,
 }
 
 impl MultiRangeMap {
+    fn convert(&self, idx: usize) -> Result<usize, OverflowError> {
+        for (rm, &line) in self.range_maps.iter().zip(self.lines.iter()) {
+            match rm.convert(idx) {
+                Ok(result) => return Ok(result),
+                Err(ConvertError::Overflow) => return Err(OverflowError { line }),
+                Err(ConvertError::OutOfRange) => {}
+            }
+        }
+        Ok(idx)
+    }
+
+    fn validate(&self) -> Vec<RangeIssueKind> {
+        let mut issues = Vec::new();
+        for (i, (rm, &line)) in self.range_maps.iter().zip(self.lines.iter()).enumerate() {
+            if rm.overflows() {
+                issues.push(RangeIssueKind::Overflow { line });
+            }
+            for (other, &other_line) in self.range_maps.iter().zip(self.lines.iter()).skip(i + 1) {
+                let start = std::cmp::max(rm.source_start, other.source_start);
+                let end = std::cmp::min(
+                    rm.source_start.saturating_add(rm.range_length),
+                    other.source_start.saturating_add(other.range_length),
+                );
+                if start < end {
+                    issues.push(RangeIssueKind::Overlap {
+                        first: line,
+                        second: other_line,
+                    });
+                }
+            }
+        }
+        issues
+    }
+
+    // equivalent table which covers every index exactly once; earlier range
+    // maps take precedence where sources overlap, just like in `convert`
+    fn to_sorted(&self) -> Result<SortedRangeMap, OverflowError> {
+        if let Some(i) = self.range_maps.iter().position(|rm| rm.overflows()) {
+            return Err(OverflowError {
+                line: self.lines[i],
+            });
+        }
+        let mut range_maps = Vec::new();
+        let everything = 0..usize::MAX;
+        let mut unmapped = vec![everything];
+        for rm in self.range_maps.iter() {
+            let mut remaining = Vec::new();
+            for r in unmapped {
+                let (mapped, rest) = rm.convert_range(r);
+                if let Some(mapped) = mapped {
+                    range_maps.push(RangeMap {
+                        destination_start: mapped.start,
+                        source_start: rm.source_start + (mapped.start - rm.destination_start),
+                        range_length: mapped.len(),
+                    });
+                }
+                remaining.extend(rest);
+            }
+            unmapped = remaining;
+        }
+        range_maps.extend(unmapped.into_iter().map(|r| RangeMap {
+            destination_start: r.start,
+            source_start: r.start,
+            range_length: r.len(),
+        }));
+        Ok(SortedRangeMap::new(range_maps))
+    }
+}
+
+// non-overlapping range maps sorted by source which together cover
+// `0..usize::MAX`, so every lookup is a binary search
+#[derive(Debug)]
+struct SortedRangeMap {
+    range_maps: Vec<RangeMap>,
+}
+
+impl SortedRangeMap {
+    fn new(mut range_maps: Vec<RangeMap>) -> Self {
+        range_maps.sort_unstable_by_key(|rm| rm.source_start);
+        // merge neighbors which shift by the same offset
+        let mut merged: Vec<RangeMap> = Vec::new();
+        for rm in range_maps.into_iter().filter(|rm| rm.range_length > 0) {
+            if let Some(last) = merged.last_mut() {
+                if last.source_start + last.range_length == rm.source_start
+                    && last.destination_start + last.range_length == rm.destination_start
+                {
+                    last.range_length += rm.range_length;
+                    continue;
+                }
+            }
+            merged.push(rm);
+        }
+        Self { range_maps: merged }
+    }
+
+    fn identity() -> Self {
+        Self::new(vec![RangeMap {
+            destination_start: 0,
+            source_start: 0,
+            range_length: usize::MAX,
+        }])
+    }
+
     fn convert(&self, idx: usize) -> usize {
+        let i = self.range_maps.partition_point(|rm| rm.source_start <= idx);
+        self.range_maps[i - 1].convert(idx).unwrap_or(idx)
+    }
+
+    fn convert_range(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let first = self
+            .range_maps
+            .partition_point(|rm| rm.source_start <= range.start)
+            .saturating_sub(1);
+        self.range_maps[first..]
+            .iter()
+            .take_while(|rm| rm.source_start < range.end)
+            .filter_map(|rm| rm.convert_range(range.clone()).0)
+            .collect::<Vec<Range<usize>>>()
+    }
+
+    // single table equivalent to applying `self` first and `next` second
+    fn compose(&self, next: &SortedRangeMap) -> SortedRangeMap {
+        let mut range_maps = Vec::new();
         for rm in self.range_maps.iter() {
-            if let Ok(result) = rm.convert(idx) {
-                return result;
+            let start = rm.destination_start;
+            let end = rm.destination_start + rm.range_length;
+            let first = next
+                .range_maps
+                .partition_point(|n| n.source_start <= start)
+                .saturating_sub(1);
+            for n in next.range_maps[first..]
+                .iter()
+                .take_while(|n| n.source_start < end)
+            {
+                let overlap_start = std::cmp::max(start, n.source_start);
+                let overlap_end = std::cmp::min(end, n.source_start + n.range_length);
+                if overlap_start >= overlap_end {
+                    continue;
+                }
+                range_maps.push(RangeMap {
+                    destination_start: n.destination_start + (overlap_start - n.source_start),
+                    source_start: rm.source_start + (overlap_start - start),
+                    range_length: overlap_end - overlap_start,
+                });
+            }
+        }
+        SortedRangeMap::new(range_maps)
+    }
+
+    // only possible if every destination is hit exactly once
+    fn invert(&self) -> Result<SortedRangeMap, NotInvertibleError> {
+        let inverse = SortedRangeMap::new(
+            self.range_maps
+                .iter()
+                .map(|rm| RangeMap {
+                    destination_start: rm.source_start,
+                    source_start: rm.destination_start,
+                    range_length: rm.range_length,
+                })
+                .collect::<Vec<RangeMap>>(),
+        );
+        let mut end = 0;
+        for rm in inverse.range_maps.iter() {
+            if rm.source_start < end {
+                return Err(NotInvertibleError::Collision {
+                    destination: rm.source_start,
+                });
+            }
+            if rm.source_start > end {
+                return Err(NotInvertibleError::Gap { destination: end });
             }
+            end = rm.source_start + rm.range_length;
+        }
+        if end != usize::MAX {
+            return Err(NotInvertibleError::Gap { destination: end });
+        }
+        Ok(inverse)
+    }
+}
+
+#[derive(Debug)]
+enum NotInvertibleError {
+    // several sources map to `destination`
+    Collision { destination: usize },
+    // no source maps to `destination`
+    Gap { destination: usize },
+}
+
+impl fmt::Display for NotInvertibleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Collision { destination } => {
+                write!(f, "several sources map to destination {destination}")
+            }
+            Self::Gap { destination } => write!(f, "no source maps to destination {destination}"),
         }
-        idx
     }
 }
 
+impl Error for NotInvertibleError {}
+
 #[derive(Debug)]
 struct RangeMap {
     destination_start: usize,
@@ -170,20 +541,46 @@ struct RangeMap {
 }
 
 impl RangeMap {
-    fn convert(&self, idx: usize) -> Result<usize, OutOfRangeError> {
-        // if idx >= self.source_start && idx < self.source_start + self.range_length {
-        //     let result = self.destination_start + (idx - self.source_start);
-        //     Ok(result)
-        // } else {
-        //     Err(OutOfRangeError)
-        // }
-        if idx < self.source_start || idx >= self.source_start + self.range_length {
-            Err(OutOfRangeError)
+    fn convert(&self, idx: usize) -> Result<usize, ConvertError> {
+        // compare offsets rather than computing the end of the source range,
+        // which might not fit into `usize`
+        if idx < self.source_start || idx - self.source_start >= self.range_length {
+            Err(ConvertError::OutOfRange)
         } else {
-            let result = self.destination_start + (idx - self.source_start);
-            Ok(result)
+            self.destination_start
+                .checked_add(idx - self.source_start)
+                .ok_or(ConvertError::Overflow)
+        }
+    }
+
+    fn overflows(&self) -> bool {
+        self.source_start.checked_add(self.range_length).is_none()
+            || self
+                .destination_start
+                .checked_add(self.range_length)
+                .is_none()
+    }
+
+    // split `range` into the part covered by this map (already converted) and
+    // the uncovered parts before and after it
+    fn convert_range(&self, range: Range<usize>) -> (Option<Range<usize>>, Vec<Range<usize>>) {
+        let source_end = self.source_start + self.range_length;
+        let start = std::cmp::max(range.start, self.source_start);
+        let end = std::cmp::min(range.end, source_end);
+        if start >= end {
+            return (None, vec![range]);
         }
+        let mapped = self.destination_start + (start - self.source_start)
+            ..self.destination_start + (end - self.source_start);
+        let rest = [range.start..start, end..range.end]
+            .into_iter()
+            .filter(|r| !r.is_empty())
+            .collect::<Vec<Range<usize>>>();
+        (Some(mapped), rest)
     }
 }
 
-struct OutOfRangeError;
+enum ConvertError {
+    OutOfRange,
+    Overflow,
+}
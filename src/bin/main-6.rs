@@ -1,4 +1,4 @@
-use std::fs;
+use std::{env, error::Error, fmt, fs, str::FromStr};
 
 fn main() {
     let filename = "inputs/6.txt";
@@ -10,49 +10,231 @@ fn main() {
     // part 1
     let time = extract_numbers_after_colon(content[0]);
     let record_distance = extract_numbers_after_colon(content[1]);
-    let possible_distances = time
-        .into_iter()
-        .map(compute_distances)
-        .collect::<Vec<Vec<usize>>>();
-    let nways = possible_distances
-        .into_iter()
-        .zip(record_distance)
-        .map(|(pd, rd)| pd.iter().filter(|&&d| d > rd).count())
-        .collect::<Vec<usize>>();
+    let nways = time
+        .iter()
+        .zip(record_distance.iter())
+        .map(|(&t, &rd)| Boat::STANDARD.count_winning_hold_times(t, rd))
+        .collect::<Result<Vec<u128>, OverflowError>>()
+        .unwrap_or_else(|e| panic!("{e}"));
     println!(
         "When you multiply the number of ways you can beat the record together you get {:?}.",
-        nways.iter().product::<usize>()
+        nways.iter().product::<u128>()
     );
 
-    // brute force part 2
-    let time = extract_single_number_after_colon(content[0]);
-    let record_distance = extract_single_number_after_colon(content[1]);
-    let possible_distances = compute_distances(time);
-    let nways = possible_distances
-        .into_iter()
-        .filter(|&pd| pd > record_distance)
-        .count();
+    let single_time = extract_single_number_after_colon(content[0]);
+    let single_record_distance = extract_single_number_after_colon(content[1]);
+    let nways = Boat::STANDARD
+        .count_winning_hold_times(single_time, single_record_distance)
+        .unwrap_or_else(|e| panic!("{e}"));
     println!(
         "There are {nways} ways in which you can beat the record in this one much longer race."
     );
+
+    // `--report` lists every race for every boat given as
+    // `--boat rate=<r>,cap=<c>,decay=<d>` (all keys optional), or for the
+    // standard boat if there are none
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|a| a == "--report") {
+        let mut boats = args
+            .windows(2)
+            .filter(|w| w[0] == "--boat")
+            .map(|w| {
+                w[1].parse::<Boat>()
+                    .unwrap_or_else(|e| panic!("Could not convert \"{}\" to `Boat`: {e}", w[1]))
+            })
+            .collect::<Vec<Boat>>();
+        if boats.is_empty() {
+            boats.push(Boat::STANDARD);
+        }
+        for (i, (&t, &rd)) in time.iter().zip(record_distance.iter()).enumerate() {
+            for (j, boat) in boats.iter().enumerate() {
+                let nways = boat
+                    .count_winning_hold_times(t, rd)
+                    .unwrap_or_else(|e| panic!("{e}"));
+                let (hold, distance) = boat.best_hold_time(t).unwrap_or_else(|e| panic!("{e}"));
+                let margin = distance as i128 - rd as i128;
+                println!(
+                    "Race {} ({t} ms, record {rd} mm), boat {}: {nways} ways to win, best is holding for {hold} ms to travel {distance} mm (margin {margin} mm).",
+                    i + 1,
+                    j + 1
+                );
+            }
+        }
+    }
 }
 
-fn extract_numbers_after_colon(s: &str) -> Vec<usize> {
+fn extract_numbers_after_colon(s: &str) -> Vec<u128> {
     s.split(':').collect::<Vec<&str>>()[1]
         .split(' ')
         .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect::<Vec<usize>>()
-}
-
-fn compute_distances(t: usize) -> Vec<usize> {
-    (0..t + 1).map(|ti| (t - ti) * ti).collect::<Vec<usize>>()
+        .map(|s| s.parse::<u128>().unwrap())
+        .collect::<Vec<u128>>()
 }
 
-fn extract_single_number_after_colon(s: &str) -> usize {
+fn extract_single_number_after_colon(s: &str) -> u128 {
     s.split(':').collect::<Vec<&str>>()[1]
         .split(' ')
         .collect::<String>()
-        .parse::<usize>()
+        .parse::<u128>()
         .unwrap()
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Boat {
+    // speed gained per ms of holding the button
+    charge_rate: u128,
+    max_speed: Option<u128>,
+    // speed lost per ms of moving
+    decay: u128,
+}
+
+impl Boat {
+    const STANDARD: Boat = Boat {
+        charge_rate: 1,
+        max_speed: None,
+        decay: 0,
+    };
+
+    fn distance(&self, t: u128, h: u128) -> Result<u128, OverflowError> {
+        let overflow = OverflowError { t };
+        let mut speed = self.charge_rate.checked_mul(h).ok_or(overflow)?;
+        if let Some(max_speed) = self.max_speed {
+            speed = std::cmp::min(speed, max_speed);
+        }
+        let moving = t - h;
+        if self.decay == 0 {
+            return speed.checked_mul(moving).ok_or(overflow);
+        }
+        // sum of `speed - k * decay` over all ms `k` before the boat stops
+        let n = std::cmp::min(moving, speed.div_ceil(self.decay));
+        let lost = self
+            .decay
+            .checked_mul(n)
+            .and_then(|d| d.checked_mul(n.saturating_sub(1)))
+            .ok_or(overflow)?
+            / 2;
+        Ok(n.checked_mul(speed).ok_or(overflow)? - lost)
+    }
+
+    fn count_winning_hold_times(&self, t: u128, record: u128) -> Result<u128, OverflowError> {
+        if *self == Self::STANDARD {
+            return Ok(count_winning_hold_times(t, record));
+        }
+        let mut count = 0;
+        for h in 0..=t {
+            if self.distance(t, h)? > record {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    // shortest hold time that goes the farthest, and how far that is
+    fn best_hold_time(&self, t: u128) -> Result<(u128, u128), OverflowError> {
+        if *self == Self::STANDARD {
+            return Ok((t / 2, self.distance(t, t / 2)?));
+        }
+        let mut best = (0, 0);
+        for h in 0..=t {
+            let d = self.distance(t, h)?;
+            if d > best.1 {
+                best = (h, d);
+            }
+        }
+        Ok(best)
+    }
+}
+
+impl FromStr for Boat {
+    type Err = ParseBoatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut boat = Boat::STANDARD;
+        for setting in s.split(',') {
+            let (key, value) = setting.split_once('=').ok_or(ParseBoatError)?;
+            let value = value.parse::<u128>().map_err(|_| ParseBoatError)?;
+            match key {
+                "rate" => boat.charge_rate = value,
+                "cap" => boat.max_speed = Some(value),
+                "decay" => boat.decay = value,
+                _ => return Err(ParseBoatError),
+            }
+        }
+        Ok(boat)
+    }
+}
+
+#[derive(Debug)]
+struct ParseBoatError;
+
+impl fmt::Display for ParseBoatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for ParseBoatError {}
+
+// holding for `h` out of `t` ms travels `(t - h) * h`; the hold times beating
+// `record` lie strictly between the roots of `h^2 - t h + record = 0`, i.e.,
+// `t / 2 -+ sqrt(t^2 / 4 - record)`
+fn count_winning_hold_times(t: u128, record: u128) -> u128 {
+    // a distance that does not fit into a u128 certainly beats the record
+    let beats_record = |h: u128| (t - h).checked_mul(h).is_none_or(|d| d > record);
+
+    let lo = match (t - t / 2).checked_mul(t / 2) {
+        // the best we can do is to hold for half of the race
+        Some(best) if best <= record => return 0,
+        // `best = floor(t^2 / 4)`, so `best - record` approximates the
+        // discriminant without ever computing `t^2`; together with rounding
+        // down in the integer square root, the estimate for the lower root is
+        // off by at most two; fix it up by checking the actual distances,
+        // which also handles hitting the record exactly
+        Some(best) => {
+            let discriminant = best - record;
+            let mut lo = t / 2 - std::cmp::min(discriminant.isqrt(), t / 2);
+            while !beats_record(lo) {
+                lo += 1;
+            }
+            while lo > 0 && beats_record(lo - 1) {
+                lo -= 1;
+            }
+            lo
+        }
+        // `best` beats any record; distances grow up to `t / 2`, so search
+        // for the shortest winning hold time there
+        None => {
+            let (mut lo, mut hi) = (0, t / 2);
+            while lo < hi {
+                let mid = lo + (hi - lo) / 2;
+                if beats_record(mid) {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+            lo
+        }
+    };
+    // distances are symmetric around t / 2, so the winning hold times are
+    // `lo..=t - lo`; holding for 0 ms never wins, so `lo >= 1` and the count
+    // always fits
+    t - 2 * lo + 1
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OverflowError {
+    t: u128,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "distances in a race of {} ms do not fit into a u128",
+            self.t
+        )
+    }
+}
+
+impl Error for OverflowError {}
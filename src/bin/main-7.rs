@@ -1,11 +1,17 @@
-use std::{cmp::Ordering, collections::HashMap, fs, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    fmt, fs,
+    str::FromStr,
+};
 
 fn main() {
     let filename = "inputs/7.txt";
     let content =
         fs::read_to_string(filename).expect("Should have been able to read the input file");
 
-    let mut hands_bids = content
+    let hands_bids = content
         .lines()
         .map(|l| {
             let l_split = l.split(' ').collect::<Vec<&str>>();
@@ -18,17 +24,185 @@ fn main() {
             (hand, bid)
         })
         .collect::<Vec<(Hand, usize)>>();
-    // TODO avoid clone here?!
-    hands_bids.sort_unstable_by_key(|k| k.0.clone());
-    let winnings = hands_bids
+
+    // compare every hand under the plain and the joker rules instead of
+    // solving, either as a readable report or as csv
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let report_requested = args.iter().any(|a| a == "--report");
+    let csv_requested = args.iter().any(|a| a == "--csv");
+    if report_requested && csv_requested {
+        panic!("Expected either `--report` or `--csv`, not both");
+    }
+    if report_requested || csv_requested {
+        let rows = compare_rules(&hands_bids, &Rules::standard(), &Rules::jokers());
+        if report_requested {
+            print!("{}", report(&rows));
+        } else {
+            print!("{}", to_csv(&rows));
+        }
+        return;
+    }
+
+    let winnings = compute_winnings(&hands_bids, &Rules::standard());
+    println!("The total winnings are {winnings}.");
+
+    let winnings = compute_winnings(&hands_bids, &Rules::jokers());
+    println!("The total winnings with jokers are {winnings}.");
+
+    // house variants, e.g. `--rules order=23456789TQKAJ,wild=J,tie=sorted`
+    for spec in args.windows(2).filter(|w| w[0] == "--rules").map(|w| &w[1]) {
+        let rules = spec
+            .parse::<Rules>()
+            .unwrap_or_else(|_| panic!("Could not convert \"{spec}\" to `Rules`"));
+        let winnings = compute_winnings(&hands_bids, &rules);
+        println!("The total winnings with rules \"{spec}\" are {winnings}.");
+    }
+}
+
+fn compute_winnings(hands_bids: &[(Hand, usize)], rules: &Rules) -> usize {
+    compute_ranks(hands_bids, rules)
+        .iter()
+        .zip(hands_bids.iter())
+        .map(|(rank, (_, b))| rank * b)
+        .sum::<usize>()
+}
+
+// rank of each hand (the weakest has rank 1) in the order of `hands_bids`
+fn compute_ranks(hands_bids: &[(Hand, usize)], rules: &Rules) -> Vec<usize> {
+    let mut keys = hands_bids
         .iter()
         .enumerate()
-        .map(|(i, (_, b))| (i + 1) * b)
-        .sum::<usize>();
-    println!("The total winnings are {winnings}.");
+        .map(|(i, (h, b))| (rules.sort_key(h), *b, i))
+        .collect::<Vec<((HandType, Vec<usize>), usize, usize)>>();
+    keys.sort_unstable();
+    let mut ranks = vec![0; hands_bids.len()];
+    for (rank, (_, _, i)) in keys.into_iter().enumerate() {
+        ranks[i] = rank + 1;
+    }
+    ranks
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Debug)]
+struct Outcome {
+    hand_type: HandType,
+    rank: usize,
+    winnings: usize,
+}
+
+#[derive(Debug)]
+struct ReportRow<'a> {
+    hand: &'a Hand,
+    bid: usize,
+    plain: Outcome,
+    jokers: Outcome,
+}
+
+fn compare_rules<'a>(
+    hands_bids: &'a [(Hand, usize)],
+    plain: &Rules,
+    jokers: &Rules,
+) -> Vec<ReportRow<'a>> {
+    let plain_ranks = compute_ranks(hands_bids, plain);
+    let joker_ranks = compute_ranks(hands_bids, jokers);
+    hands_bids
+        .iter()
+        .zip(plain_ranks.into_iter().zip(joker_ranks))
+        .map(|((hand, bid), (plain_rank, joker_rank))| ReportRow {
+            hand,
+            bid: *bid,
+            plain: Outcome {
+                hand_type: plain.hand_type(hand),
+                rank: plain_rank,
+                winnings: plain_rank * bid,
+            },
+            jokers: Outcome {
+                hand_type: jokers.hand_type(hand),
+                rank: joker_rank,
+                winnings: joker_rank * bid,
+            },
+        })
+        .collect::<Vec<ReportRow>>()
+}
+
+fn report(rows: &[ReportRow]) -> String {
+    let mut out = format!(
+        "{:<8} {:>6} {:<16} {:>6} {:>10} {:<16} {:>6} {:>10} {:>7}\n",
+        "Hand", "Bid", "Type", "Rank", "Winnings", "Joker type", "Rank", "Winnings", "Change"
+    );
+    for r in rows {
+        out.push_str(&format!(
+            "{:<8} {:>6} {:<16} {:>6} {:>10} {:<16} {:>6} {:>10} {:>+7}\n",
+            r.hand.to_string(),
+            r.bid,
+            r.plain.hand_type.to_string(),
+            r.plain.rank,
+            r.plain.winnings,
+            r.jokers.hand_type.to_string(),
+            r.jokers.rank,
+            r.jokers.winnings,
+            r.jokers.rank as i64 - r.plain.rank as i64,
+        ));
+    }
+
+    out.push_str(&format!(
+        "\n{:<16} {:>6} {:>6}\n",
+        "Hand type", "Plain", "Jokers"
+    ));
+    for (i, c) in CATEGORIES.iter().enumerate() {
+        let count = |f: fn(&ReportRow) -> HandType| rows.iter().filter(|r| f(r).0 == i).count();
+        let plain = count(|r| r.plain.hand_type);
+        let jokers = count(|r| r.jokers.hand_type);
+        if plain > 0 || jokers > 0 {
+            out.push_str(&format!("{:<16} {plain:>6} {jokers:>6}\n", c.name));
+        }
+    }
+
+    let mut identical: HashMap<&Hand, Vec<usize>> = HashMap::new();
+    for (i, r) in rows.iter().enumerate() {
+        identical.entry(r.hand).or_default().push(i + 1);
+    }
+    let mut ties = identical
+        .into_iter()
+        .filter(|(_, lines)| lines.len() > 1)
+        .collect::<Vec<(&Hand, Vec<usize>)>>();
+    ties.sort_unstable_by_key(|(_, lines)| lines[0]);
+    out.push_str("\nTies:\n");
+    if ties.is_empty() {
+        out.push_str("none\n");
+    }
+    for (hand, lines) in ties {
+        let lines = lines
+            .iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        out.push_str(&format!("{hand} on lines {lines}\n"));
+    }
+    out
+}
+
+fn to_csv(rows: &[ReportRow]) -> String {
+    let mut csv = String::from(
+        "hand,bid,type,rank,winnings,joker_type,joker_rank,joker_winnings,rank_change\n",
+    );
+    for r in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            r.hand,
+            r.bid,
+            r.plain.hand_type,
+            r.plain.rank,
+            r.plain.winnings,
+            r.jokers.hand_type,
+            r.jokers.rank,
+            r.jokers.winnings,
+            r.jokers.rank as i64 - r.plain.rank as i64,
+        ));
+    }
+    csv
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 struct Hand {
     cards: Vec<Card>,
 }
@@ -45,38 +219,136 @@ impl FromStr for Hand {
     }
 }
 
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.cards
+                .iter()
+                .map(|&c| char::from(c))
+                .collect::<String>()
+        )
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct ParseError;
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let hand_type_self = HandType::from(&self.cards);
-        let hand_type_other = HandType::from(&other.cards);
-        let cmp = hand_type_self.cmp(&hand_type_other);
-        if cmp != Ordering::Equal {
-            return cmp;
-        } else {
-            for (cs, co) in self.cards.iter().zip(other.cards.iter()) {
-                if cs == co {
-                    continue;
-                } else {
-                    return cs.cmp(co);
-                }
-            }
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TieBreak {
+    // compare the cards one by one in the order they were dealt
+    InOrder,
+    // compare the cards from strongest to weakest
+    Sorted,
+}
+
+#[derive(Clone, Debug)]
+struct Rules {
+    // from weakest to strongest
+    card_order: Vec<Card>,
+    wild: Option<Card>,
+    tie_break: TieBreak,
+}
+
+impl Rules {
+    fn standard() -> Self {
+        Self {
+            card_order: "23456789TJQKA"
+                .chars()
+                .map(|c| Card::try_from(c).unwrap())
+                .collect::<Vec<Card>>(),
+            wild: None,
+            tie_break: TieBreak::InOrder,
         }
-        Ordering::Equal
+    }
+
+    fn jokers() -> Self {
+        Self {
+            card_order: "J23456789TQKA"
+                .chars()
+                .map(|c| Card::try_from(c).unwrap())
+                .collect::<Vec<Card>>(),
+            wild: Some(Card::Jack),
+            tie_break: TieBreak::InOrder,
+        }
+    }
+
+    fn strength(&self, card: Card) -> usize {
+        self.card_order.iter().position(|&c| c == card).unwrap()
+    }
+
+    fn hand_type(&self, hand: &Hand) -> HandType {
+        HandType::classify(&hand.cards, self.wild)
+    }
+
+    // hands compare by hand type first and card strengths second; computing
+    // this once per hand avoids reclassifying hands during sorting
+    fn sort_key(&self, hand: &Hand) -> (HandType, Vec<usize>) {
+        let mut strengths = hand
+            .cards
+            .iter()
+            .map(|&c| self.strength(c))
+            .collect::<Vec<usize>>();
+        if self.tie_break == TieBreak::Sorted {
+            strengths.sort_unstable_by(|x, y| y.cmp(x));
+        }
+        (self.hand_type(hand), strengths)
     }
 }
 
-impl PartialOrd for Hand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl FromStr for Rules {
+    type Err = ParseError;
+
+    // comma-separated `key=value` pairs overriding the standard rules
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Rules::standard();
+        for setting in s.split(',') {
+            let (key, value) = setting.split_once('=').ok_or(ParseError)?;
+            match key {
+                "order" => {
+                    rules.card_order = value
+                        .chars()
+                        .map(Card::try_from)
+                        .collect::<Result<Vec<Card>, ParseError>>()?;
+                }
+                "wild" => {
+                    let mut chars = value.chars();
+                    rules.wild = match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(Card::try_from(c)?),
+                        _ if value == "none" => None,
+                        _ => return Err(ParseError),
+                    };
+                }
+                "tie" => {
+                    rules.tie_break = match value {
+                        "in-order" => TieBreak::InOrder,
+                        "sorted" => TieBreak::Sorted,
+                        _ => return Err(ParseError),
+                    };
+                }
+                _ => return Err(ParseError),
+            }
+        }
+        // every card needs exactly one strength
+        let distinct = rules.card_order.iter().collect::<HashSet<&Card>>();
+        if distinct.len() != 13 || rules.card_order.len() != 13 {
+            return Err(ParseError);
+        }
+        Ok(rules)
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum Card {
-    Jack, // part two
     Two,
     Three,
     Four,
@@ -86,7 +358,7 @@ enum Card {
     Eight,
     Nine,
     Ten,
-    // Jack,  // part one
+    Jack,
     Queen,
     King,
     Ace,
@@ -115,70 +387,114 @@ impl TryFrom<char> for Card {
     }
 }
 
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
-}
-
-impl From<&Vec<Card>> for HandType {
-    fn from(cards: &Vec<Card>) -> Self {
-        fn best_cards_without_jack(cards: &[Card]) -> Vec<Card> {
-            // replace all Jacks and choose the hand type with the highest rating
-            let mut choices = Vec::new();
-            let mut stack = Vec::new();
-            stack.push(cards.to_vec());
-            'outer: while let Some(v) = stack.pop() {
-                for (i, c) in v.iter().enumerate() {
-                    if *c == Card::Jack {
-                        // try if duplicating existing cards helps
-                        for r in v.iter().filter(|&&c| c != Card::Jack) {
-                            let mut tmp = v.to_vec();
-                            tmp[i] = *r;
-                            stack.push(tmp);
-                        }
-                        // try if adding an ace helps
-                        let mut tmp = v.to_vec();
-                        tmp[i] = Card::Ace;
-                        stack.push(tmp);
-                        continue 'outer;
-                    }
-                }
-                assert!(!v.iter().any(|&c| c == Card::Jack));
-                choices.push(Hand { cards: v.to_vec() });
-            }
-            choices.sort_unstable();
-            choices.last().unwrap().cards.clone()
+impl From<Card> for char {
+    fn from(card: Card) -> Self {
+        match card {
+            Card::Ace => 'A',
+            Card::King => 'K',
+            Card::Queen => 'Q',
+            Card::Jack => 'J',
+            Card::Ten => 'T',
+            Card::Nine => '9',
+            Card::Eight => '8',
+            Card::Seven => '7',
+            Card::Six => '6',
+            Card::Five => '5',
+            Card::Four => '4',
+            Card::Three => '3',
+            Card::Two => '2',
         }
+    }
+}
 
-        let cards = best_cards_without_jack(cards); // for part two
+struct Category {
+    name: &'static str,
+    // sizes of the groups of equal cards a hand needs to contain, largest first
+    groups: &'static [usize],
+}
+
+// hand categories from weakest to strongest; hands of any size belong to the
+// strongest category whose groups they contain
+const CATEGORIES: &[Category] = &[
+    Category {
+        name: "High card",
+        groups: &[],
+    },
+    Category {
+        name: "One pair",
+        groups: &[2],
+    },
+    Category {
+        name: "Two pair",
+        groups: &[2, 2],
+    },
+    Category {
+        name: "Three pair",
+        groups: &[2, 2, 2],
+    },
+    Category {
+        name: "Three of a kind",
+        groups: &[3],
+    },
+    Category {
+        name: "Full house",
+        groups: &[3, 2],
+    },
+    Category {
+        name: "Four of a kind",
+        groups: &[4],
+    },
+    Category {
+        name: "Five of a kind",
+        groups: &[5],
+    },
+    Category {
+        name: "Six of a kind",
+        groups: &[6],
+    },
+    Category {
+        name: "Seven of a kind",
+        groups: &[7],
+    },
+];
+
+// index into `CATEGORIES`
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct HandType(usize);
 
+impl HandType {
+    fn classify(cards: &[Card], wild: Option<Card>) -> Self {
         let mut counter: HashMap<Card, usize> = HashMap::new();
+        let mut wild_count = 0;
         for c in cards.iter() {
-            *counter.entry(*c).or_default() += 1;
+            if Some(*c) == wild {
+                wild_count += 1;
+            } else {
+                *counter.entry(*c).or_default() += 1;
+            }
         }
         let mut counter = counter.values().copied().collect::<Vec<usize>>();
-        counter.sort_unstable();
-        if counter == vec![5] {
-            HandType::FiveOfAKind
-        } else if counter == vec![1, 4] {
-            HandType::FourOfAKind
-        } else if counter == vec![2, 3] {
-            HandType::FullHouse
-        } else if counter == vec![1, 1, 3] {
-            HandType::ThreeOfAKind
-        } else if counter == vec![1, 2, 2] {
-            HandType::TwoPair
-        } else if counter == vec![1, 1, 1, 2] {
-            HandType::OnePair
-        } else {
-            assert!(counter == vec![1, 1, 1, 1, 1]);
-            HandType::HighCard
-        }
+        counter.sort_unstable_by(|a, b| b.cmp(a));
+
+        // a category is within reach if the wild cards can fill up the missing
+        // cards when matching the largest groups with the largest groups
+        let missing = |groups: &[usize]| {
+            groups
+                .iter()
+                .enumerate()
+                .map(|(i, &g)| g.saturating_sub(counter.get(i).copied().unwrap_or(0)))
+                .sum::<usize>()
+        };
+        let i = CATEGORIES
+            .iter()
+            .rposition(|c| missing(c.groups) <= wild_count)
+            .unwrap();
+        HandType(i)
+    }
+}
+
+impl fmt::Display for HandType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", CATEGORIES[self.0].name)
     }
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, fs};
+use std::{collections::HashMap, env, error::Error, fmt, fs};
 
 fn main() {
     let filename = "inputs/8.txt";
@@ -7,13 +7,36 @@ fn main() {
 
     let mut content = content.lines().collect::<Vec<&str>>();
     let instructions = parse_instructions(content[0]).unwrap();
-    let network = parse_network(&content.split_off(2));
+    let network = parse_network(&content.split_off(2))
+        .unwrap_or_else(|e| panic!("Could not parse network: {e}"));
 
-    let steps = count_steps(&network, &instructions);
-    println!("{steps} steps are required to reach ZZZ.");
+    // export the network to Graphviz instead of solving, e.g. `--dot` or
+    // `--dot-from 11A` to only keep the nodes visited when walking from 11A
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|a| a == "--dot") {
+        print!("{}", network.to_dot(None));
+        return;
+    }
+    if let Some(i) = args.iter().position(|a| a == "--dot-from") {
+        let start = args.get(i + 1).expect("Expected `--dot-from <node>`");
+        let start = network.id(start).unwrap_or_else(|e| panic!("{e}"));
+        let visits = first_visits(&network, &instructions, start);
+        print!("{}", network.to_dot(Some(&visits)));
+        return;
+    }
+
+    match count_steps(&network, &instructions) {
+        Ok(steps) => println!("{steps} steps are required to reach ZZZ."),
+        Err(e) => println!("{e}"),
+    }
 
-    let ghost_steps = count_ghost_steps(&network, &instructions);
-    println!("{ghost_steps} ghost steps are required to reach **Z.");
+    match count_ghost_steps(&network, &instructions) {
+        Ok(Some(ghost_steps)) => {
+            println!("{ghost_steps} ghost steps are required to reach **Z.")
+        }
+        Ok(None) => println!("The ghosts never all reach **Z at the same time."),
+        Err(e) => println!("{e}"),
+    }
 }
 
 fn parse_instructions(s: &str) -> Result<Vec<Direction>, ParseDirectionError> {
@@ -22,7 +45,7 @@ fn parse_instructions(s: &str) -> Result<Vec<Direction>, ParseDirectionError> {
         .collect::<Result<Vec<Direction>, ParseDirectionError>>()
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 enum Direction {
     Left,
     Right,
@@ -43,107 +66,351 @@ impl TryFrom<char> for Direction {
 #[derive(Debug)]
 struct ParseDirectionError {}
 
-fn parse_network(s: &Vec<&str>) -> HashMap<Node, (Node, Node)> {
-    let mut network = HashMap::new();
+fn parse_network(s: &[&str]) -> Result<Network, NetworkError> {
+    let mut network = Network::default();
     for l in s {
         let l = l.split(" = ").collect::<Vec<&str>>();
-        let key = l[0];
+        let key = network.intern(l[0]);
         let l = l[1][1..l[1].len() - 1].split(", ").collect::<Vec<&str>>();
-        let (left, right) = (l[0], l[1]);
-        network.insert(Node::from(key), (Node::from(left), Node::from(right)));
+        let (left, right) = (network.intern(l[0]), network.intern(l[1]));
+        network.edges[key as usize] = [left, right];
     }
-    network
+    // every node that is only referenced still has its placeholder edges
+    if let Some(undefined) = network.edges.iter().position(|&e| e == UNDEFINED) {
+        let referenced_by = network
+            .edges
+            .iter()
+            .position(|e| e.contains(&(undefined as u32)))
+            .unwrap();
+        return Err(NetworkError::UndefinedNode {
+            name: network.names[undefined].clone(),
+            referenced_by: network.names[referenced_by].clone(),
+        });
+    }
+    Ok(network)
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
-struct Node {
-    key: String,
+const UNDEFINED: [u32; 2] = [u32::MAX; 2];
+
+// nodes are interned into dense ids, so that every step is a single lookup
+#[derive(Debug, Default)]
+struct Network {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+    // left and right neighbor of each node
+    edges: Vec<[u32; 2]>,
+    // whether the name of each node ends in 'A' or 'Z', respectively
+    is_start: Vec<bool>,
+    is_goal: Vec<bool>,
 }
 
-impl From<&str> for Node {
-    fn from(s: &str) -> Self {
-        Node { key: s.to_string() }
+impl Network {
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        // filled in once we encounter the definition of the node
+        self.edges.push(UNDEFINED);
+        self.is_start.push(name.ends_with('A'));
+        self.is_goal.push(name.ends_with('Z'));
+        id
+    }
+
+    fn step(&self, node: u32, direction: Direction) -> u32 {
+        self.edges[node as usize][direction as usize]
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    fn id(&self, name: &str) -> Result<u32, NetworkError> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| NetworkError::MissingNode(name.to_string()))
+    }
+
+    // starts are drawn green and goals red; if `visits` is given, only the
+    // visited nodes are kept and filled from red (early) to blue (late)
+    fn to_dot(&self, visits: Option<&[Option<usize>]>) -> String {
+        let keep = |n: usize| visits.is_none_or(|v| v[n].is_some());
+        let last_visit = visits
+            .into_iter()
+            .flatten()
+            .flatten()
+            .max()
+            .copied()
+            .unwrap_or(0);
+        let mut out = String::from("digraph network {\n    node [shape=circle];\n");
+        for (n, name) in self.names.iter().enumerate().filter(|&(n, _)| keep(n)) {
+            let mut attributes = Vec::new();
+            if self.is_start[n] {
+                attributes.push("color=green, penwidth=3".to_string());
+            } else if self.is_goal[n] {
+                attributes.push("color=red, penwidth=3".to_string());
+            }
+            if let Some(step) = visits.and_then(|v| v[n]) {
+                let hue = 0.7 * step as f64 / std::cmp::max(last_visit, 1) as f64;
+                attributes.push(format!(
+                    "label=\"{name}\\n{step}\", style=filled, fillcolor=\"{hue:.3} 0.5 1.0\""
+                ));
+            }
+            if attributes.is_empty() {
+                out.push_str(&format!("    \"{name}\";\n"));
+            } else {
+                out.push_str(&format!("    \"{name}\" [{}];\n", attributes.join(", ")));
+            }
+        }
+        for (n, &[left, right]) in self.edges.iter().enumerate().filter(|&(n, _)| keep(n)) {
+            let name = &self.names[n];
+            if left == right {
+                out.push_str(&format!(
+                    "    \"{name}\" -> \"{}\" [label=\"L/R\"];\n",
+                    self.names[left as usize]
+                ));
+                continue;
+            }
+            for (target, label) in [(left, 'L'), (right, 'R')] {
+                if keep(target as usize) {
+                    out.push_str(&format!(
+                        "    \"{name}\" -> \"{}\" [label=\"{label}\"];\n",
+                        self.names[target as usize]
+                    ));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
     }
 }
 
-fn count_steps(network: &HashMap<Node, (Node, Node)>, instructions: &Vec<Direction>) -> usize {
+// step at which a walk from `start` first visits each node, or `None` for
+// nodes it never reaches
+fn first_visits(network: &Network, instructions: &[Direction], start: u32) -> Vec<Option<usize>> {
+    let mut visits = vec![None; network.len()];
+    let mut visited = vec![false; network.len() * instructions.len()];
     let mut count = 0;
-    let mut current_node = &Node::from("AAA");
-    while *current_node != Node::from("ZZZ") {
-        current_node = match instructions[count % instructions.len()] {
-            Direction::Left => &network[&current_node].0,
-            Direction::Right => &network[&current_node].1,
-        };
+    let mut current_node = start;
+    loop {
+        let state = current_node as usize * instructions.len() + count % instructions.len();
+        if visited[state] {
+            return visits;
+        }
+        visited[state] = true;
+        visits[current_node as usize].get_or_insert(count);
+        current_node = network.step(current_node, instructions[count % instructions.len()]);
         count += 1;
     }
-    count
 }
 
-fn count_ghost_steps(network: &HashMap<Node, (Node, Node)>, instructions: &[Direction]) -> usize {
-    // idea: find periods for each node ending in 'A', then find least common
-    // multiple
-    let current_nodes = network
-        .keys()
-        .filter(|n| n.key.ends_with('A'))
-        .cloned()
-        .collect::<Vec<Node>>();
-    let periods = current_nodes
-        .iter()
-        .map(|n| determine_period(network, instructions, n))
-        .collect::<Vec<usize>>();
-    find_lcm(&periods)
+#[derive(Debug, PartialEq, Eq)]
+enum NetworkError {
+    UndefinedNode {
+        name: String,
+        referenced_by: String,
+    },
+    MissingNode(String),
+    // the walk entered a cycle without passing the target; `reachable` lists
+    // every node visited on the way, in alphabetical order
+    Unreachable {
+        target: String,
+        reachable: Vec<String>,
+    },
+    // the ghosts only meet after more steps than fit into a `usize`
+    Overflow,
 }
 
-fn determine_period(
-    network: &HashMap<Node, (Node, Node)>,
-    instructions: &[Direction],
-    starting_node: &Node,
-) -> usize {
-    // count steps until encountering first node ending in Z
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedNode {
+                name,
+                referenced_by,
+            } => write!(
+                f,
+                "node {name} is referenced by {referenced_by} but never defined"
+            ),
+            Self::MissingNode(name) => write!(f, "the network has no node {name}"),
+            Self::Unreachable { target, reachable } => write!(
+                f,
+                "{target} cannot be reached; the walk only visits {}",
+                reachable.join(", ")
+            ),
+            Self::Overflow => write!(f, "the number of ghost steps does not fit into a usize"),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+fn count_steps(network: &Network, instructions: &[Direction]) -> Result<usize, NetworkError> {
+    let start = network.id("AAA")?;
+    let goal = network.id("ZZZ")?;
+    // once a (node, instruction position) state repeats, the walk is stuck in a
+    // cycle that does not contain the goal
+    let mut visited = vec![false; network.len() * instructions.len()];
     let mut count = 0;
-    let mut current_node = starting_node;
-    while !current_node.key.ends_with('Z') {
-        current_node = match instructions[count % instructions.len()] {
-            Direction::Left => &network[&current_node].0,
-            Direction::Right => &network[&current_node].1,
-        };
+    let mut current_node = start;
+    while current_node != goal {
+        let state = current_node as usize * instructions.len() + count % instructions.len();
+        if visited[state] {
+            let mut reachable = visited
+                .chunks(instructions.len())
+                .zip(network.names.iter())
+                .filter(|(v, _)| v.contains(&true))
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<String>>();
+            reachable.sort_unstable();
+            return Err(NetworkError::Unreachable {
+                target: network.names[goal as usize].clone(),
+                reachable,
+            });
+        }
+        visited[state] = true;
+        current_node = network.step(current_node, instructions[count % instructions.len()]);
         count += 1;
     }
-    let end_node = current_node;
-    let period = count;
+    Ok(count)
+}
 
-    // make sure that one ends up at the same node after `period` number of
-    // steps
-    for _ in 0..period {
-        current_node = match instructions[count % instructions.len()] {
-            Direction::Left => &network[&current_node].0,
-            Direction::Right => &network[&current_node].1,
-        };
-        count += 1;
+fn count_ghost_steps(
+    network: &Network,
+    instructions: &[Direction],
+) -> Result<Option<usize>, NetworkError> {
+    // idea: for each node ending in 'A', find all steps at which the ghost is
+    // at a node ending in 'Z'; these eventually repeat periodically, so the
+    // first step shared by all ghosts follows from the chinese remainder
+    // theorem
+    let orbits = (0..network.len() as u32)
+        .filter(|&n| network.is_start[n as usize])
+        .map(|n| analyze_orbit(network, instructions, n))
+        .collect::<Vec<Orbit>>();
+
+    // before all ghosts entered their cycles, just check every step
+    let Some(cycle_start) = orbits.iter().map(|o| o.cycle_start).max() else {
+        return Ok(None);
+    };
+    if let Some(steps) = (0..cycle_start).find(|&t| orbits.iter().all(|o| o.is_hit(t))) {
+        return Ok(Some(steps));
+    }
+
+    // afterwards, every combination of hits within the cycles leads to one
+    // system of congruences; solve them ghost by ghost, so that combinations
+    // without a common solution are dropped as early as possible
+    let mut solutions = vec![(0, 1)];
+    for o in orbits.iter() {
+        let mut merged = Vec::new();
+        for &solution in solutions.iter() {
+            for &h in o.cycle_hits.iter() {
+                if let Some(s) = merge_congruences(solution, (h as i128, o.period as i128))? {
+                    merged.push(s);
+                }
+            }
+        }
+        // different combinations may well lead to the same congruence
+        merged.sort_unstable();
+        merged.dedup();
+        solutions = merged;
+    }
+    let mut steps = None;
+    for (remainder, modulus) in solutions {
+        // smallest solution at which all ghosts are in their cycles
+        let cycle_start = cycle_start as i128;
+        let k = std::cmp::max(
+            (cycle_start - remainder + modulus - 1).div_euclid(modulus),
+            0,
+        );
+        let solution = k
+            .checked_mul(modulus)
+            .and_then(|s| s.checked_add(remainder))
+            .and_then(|s| usize::try_from(s).ok())
+            .ok_or(NetworkError::Overflow)?;
+        steps = Some(steps.map_or(solution, |s: usize| s.min(solution)));
     }
-    assert!(current_node == end_node);
+    Ok(steps)
+}
+
+// all steps at which a ghost is at a node ending in 'Z'
+#[derive(Debug)]
+struct Orbit {
+    // hits before the ghost enters its cycle
+    pre_cycle_hits: Vec<usize>,
+    cycle_start: usize,
+    period: usize,
+    // hits during the first pass through the cycle, each repeating with
+    // `period`
+    cycle_hits: Vec<usize>,
+}
 
-    period
+impl Orbit {
+    fn is_hit(&self, t: usize) -> bool {
+        if t < self.cycle_start {
+            self.pre_cycle_hits.contains(&t)
+        } else {
+            let t = self.cycle_start + (t - self.cycle_start) % self.period;
+            self.cycle_hits.contains(&t)
+        }
+    }
 }
 
-fn find_lcm(x: &[usize]) -> usize {
-    let mut lcm = x[0];
-    for item in x.iter().skip(1) {
-        lcm = compute_lcm(lcm, *item);
+fn analyze_orbit(network: &Network, instructions: &[Direction], starting_node: u32) -> Orbit {
+    // the state of a ghost is its node and its position in the instructions;
+    // walk until a state repeats
+    let state =
+        |node: u32, count: usize| node as usize * instructions.len() + count % instructions.len();
+    let mut first_visit = vec![None; network.len() * instructions.len()];
+    let mut hits = Vec::new();
+    let mut count = 0;
+    let mut current_node = starting_node;
+    while first_visit[state(current_node, count)].is_none() {
+        first_visit[state(current_node, count)] = Some(count);
+        if network.is_goal[current_node as usize] {
+            hits.push(count);
+        }
+        current_node = network.step(current_node, instructions[count % instructions.len()]);
+        count += 1;
+    }
+    let cycle_start = first_visit[state(current_node, count)].unwrap();
+    let (pre_cycle_hits, cycle_hits) = hits.into_iter().partition(|&h| h < cycle_start);
+    Orbit {
+        pre_cycle_hits,
+        cycle_start,
+        period: count - cycle_start,
+        cycle_hits,
     }
-    lcm
 }
 
-fn compute_lcm(a: usize, b: usize) -> usize {
-    let gcd = compute_gcd(a, b);
-    a * (b / gcd)
+// combine two congruences `x = remainder (mod modulus)` into a single one,
+// or `None` if there is no common solution; moduli do not need to be coprime
+fn merge_congruences(
+    (a1, m1): (i128, i128),
+    (a2, m2): (i128, i128),
+) -> Result<Option<(i128, i128)>, NetworkError> {
+    let (g, p, _) = extended_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return Ok(None);
+    }
+    let lcm = (m1 / g).checked_mul(m2).ok_or(NetworkError::Overflow)?;
+    // `p * m1 = g (mod m2)`, so `a1 + m1 * p * (a2 - a1) / g` solves both;
+    // `|k| < m2 / g`, so `|m1 * k| < lcm` cannot overflow
+    let k = ((a2 - a1) / g % (m2 / g))
+        .checked_mul(p)
+        .ok_or(NetworkError::Overflow)?
+        % (m2 / g);
+    let x = a1.checked_add(m1 * k).ok_or(NetworkError::Overflow)?;
+    Ok(Some((x.rem_euclid(lcm), lcm)))
 }
 
-fn compute_gcd(mut a: usize, mut b: usize) -> usize {
-    while b != 0 {
-        let t = b;
-        b = a % b;
-        a = t;
+// returns `(g, p, q)` with `g = gcd(a, b) = p * a + q * b`
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, p, q) = extended_gcd(b, a % b);
+        (g, q, p - (a / b) * q)
     }
-    a
 }
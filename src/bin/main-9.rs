@@ -1,79 +1,559 @@
-use std::fs;
+use std::{env, error::Error, fmt, fs, str::FromStr};
 
 fn main() {
     let filename = "inputs/9.txt";
     let content =
         fs::read_to_string(filename).expect("Should have been able to read the input file");
 
+    // histories hold integers unless requested otherwise, e.g. `--float` for
+    // measurements or `--rational` for values like `7/2`
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if args.iter().any(|a| a == "--float") {
+        run::<f64>(&content, &args);
+    } else if args.iter().any(|a| a == "--rational") {
+        run::<Rational>(&content, &args);
+    } else {
+        run::<i128>(&content, &args);
+    }
+}
+
+fn run<T: Number + FromStr + fmt::Display>(content: &str, args: &[String]) {
     let histories = content
         .lines()
         .map(|l| {
             l.split(' ')
-                .map(|s| s.parse::<i32>().unwrap())
-                .collect::<Vec<i32>>()
+                .map(|s| {
+                    s.parse::<T>()
+                        .unwrap_or_else(|_| panic!("Could not parse \"{s}\""))
+                })
+                .collect::<Vec<T>>()
         })
-        .collect::<Vec<Vec<i32>>>();
+        .collect::<Vec<Vec<T>>>();
 
-    let sum = histories
-        .iter()
-        .map(|h| *predict(h).last().unwrap())
-        .sum::<i32>();
+    let sum = sum_extrapolated(&histories, |h| h.len() as i128).unwrap_or_else(|e| panic!("{e}"));
     println!("The sum of these extrapolated values is {sum}.");
 
-    let sum = histories
+    let sum = sum_extrapolated(&histories, |_| -1).unwrap_or_else(|e| panic!("{e}"));
+    println!("The sum of these backward-extrapolated values is {sum}.");
+
+    // report the closed form of every history, as a polynomial in its
+    // position `x`, with `x = 0` at the first value
+    if args.iter().any(|a| a == "--diagnose") {
+        for (i, h) in histories.iter().enumerate() {
+            match fit(h) {
+                Model::Polynomial {
+                    degree,
+                    coefficients,
+                } => println!(
+                    "History {}: degree {degree}, p(x) = {}",
+                    i + 1,
+                    format_polynomial(&coefficients)
+                ),
+                Model::NonPolynomial => println!(
+                    "History {}: not a polynomial sequence, its differences never all vanish",
+                    i + 1
+                ),
+                Model::Overflow => println!("History {}: the closed form overflows", i + 1),
+            }
+        }
+    }
+
+    // extrapolate every history to an arbitrary position, e.g. `--at 100` or
+    // `--at -5`
+    if let Some(i) = args.iter().position(|a| a == "--at") {
+        let position = args
+            .get(i + 1)
+            .and_then(|p| p.parse::<i128>().ok())
+            .expect("Expected `--at <position>`");
+        for (i, h) in histories.iter().enumerate() {
+            match extrapolate(h, position) {
+                Ok(value) => println!("History {}: {value}", i + 1),
+                Err(e) => println!("History {}: {e}", i + 1),
+            }
+        }
+    }
+
+    // continue every history by the next few values, e.g. `--extend 3`
+    if let Some(i) = args.iter().position(|a| a == "--extend") {
+        let k = args
+            .get(i + 1)
+            .and_then(|k| k.parse::<usize>().ok())
+            .expect("Expected `--extend <count>`");
+        for (i, h) in histories.iter().enumerate() {
+            match extend(h, k) {
+                Some(values) => {
+                    let values = values.iter().map(T::to_string).collect::<Vec<String>>();
+                    println!("History {}: {}", i + 1, values.join(" "));
+                }
+                None => println!("History {}: extending overflows", i + 1),
+            }
+        }
+    }
+}
+
+fn sum_extrapolated<T: Number>(
+    histories: &[Vec<T>],
+    position: impl Fn(&[T]) -> i128,
+) -> Result<T, OverflowError> {
+    histories.iter().try_fold(T::ZERO, |sum, h| {
+        let position = position(h);
+        let value = extrapolate(h, position)?;
+        sum.checked_add(value).ok_or(OverflowError { position })
+    })
+}
+
+// value at `position` of the polynomial through the history, where the
+// history occupies positions `0..len` and negative positions lie before it;
+// by newton's forward difference formula, this is the sum of the leading
+// differences `d_j` weighted by `binomial(position, j)`
+fn extrapolate<T: Number>(history: &[T], position: i128) -> Result<T, OverflowError> {
+    let overflow = OverflowError { position };
+    let table = history.difference_table().ok_or(overflow)?;
+    let mut value = T::ZERO;
+    // once a row vanishes, so do all rows below it
+    let vanishing = vanishing_row(&table).unwrap_or(table.len());
+    for (j, row) in table[..vanishing].iter().enumerate() {
+        let term = binomial(position, j)
+            .and_then(T::from_i128)
+            .and_then(|b| b.checked_mul(row[0]))
+            .ok_or(overflow)?;
+        value = value.checked_add(term).ok_or(overflow)?;
+    }
+    Ok(value)
+}
+
+// the history followed by its next `k` values, found by continuing the first
+// vanishing row of the difference table with zeros and summing back up
+fn extend<T: Number>(history: &[T], k: usize) -> Option<Vec<T>> {
+    let table = history.difference_table()?;
+    let vanishing = vanishing_row(&table).unwrap_or(table.len());
+    let mut row = vec![T::ZERO; table.get(vanishing).map_or(0, Vec::len) + k];
+    for above in table[..vanishing].iter().rev() {
+        row.insert(0, above[0]);
+        row = row.cumsum()?;
+    }
+    Some(row)
+}
+
+// index of the first row of the difference table that is all zeros, up to
+// the rounding error of the history in the first row; a history is only known
+// to be a polynomial sequence if there is one
+fn vanishing_row<T: Number>(table: &[Vec<T>]) -> Option<usize> {
+    let scale = scale(&table[0]);
+    table
         .iter()
-        .map(|h| {
-            let hrev = h.iter().rev().copied().collect::<Vec<i32>>();
-            *predict(&hrev).last().unwrap()
+        .enumerate()
+        .position(|(k, row)| row.iter().all(|&d| d.is_zero(scale_of_order(scale, k))))
+}
+
+// largest magnitude among `values`, against which rounding errors are judged
+fn scale<T: Number>(values: &[T]) -> f64 {
+    values.iter().map(|&v| v.magnitude()).fold(0.0, f64::max)
+}
+
+// scale of the rounding error in differences of order `k` of values of
+// magnitude `scale`; taking a difference at most doubles the error, and the
+// extra factor leaves room for the rounding of the values themselves
+fn scale_of_order(scale: f64, k: usize) -> f64 {
+    scale * 2f64.powi(k.saturating_add(4).try_into().unwrap_or(i32::MAX))
+}
+
+#[derive(Debug)]
+enum Model<F> {
+    // `coefficients[i]` belongs to `x^i`
+    Polynomial { degree: usize, coefficients: Vec<F> },
+    NonPolynomial,
+    Overflow,
+}
+
+fn fit<T: Number>(history: &[T]) -> Model<T::Quotient> {
+    let Some(table) = history.difference_table() else {
+        return Model::Overflow;
+    };
+    let Some(vanishing) = vanishing_row(&table) else {
+        return Model::NonPolynomial;
+    };
+    let leading = table[..vanishing]
+        .iter()
+        .map(|row| row[0])
+        .collect::<Vec<T>>();
+    match coefficients(&leading) {
+        Some(coefficients) => Model::Polynomial {
+            degree: vanishing.saturating_sub(1),
+            // drop terms that only stem from rounding errors in floats
+            coefficients: coefficients
+                .into_iter()
+                .map(|c| {
+                    if c.is_zero(scale_of_order(scale(history), vanishing)) {
+                        T::Quotient::ZERO
+                    } else {
+                        c
+                    }
+                })
+                .collect::<Vec<T::Quotient>>(),
+        },
+        None => Model::Overflow,
+    }
+}
+
+// expand newton's form `sum_j d_j binomial(x, j)` into powers of `x`
+fn coefficients<T: Number>(leading: &[T]) -> Option<Vec<T::Quotient>> {
+    let zero = T::Quotient::ZERO;
+    let mut coefficients = vec![zero; leading.len()];
+    // `binomial(x, j)` as a polynomial in `x`
+    let mut basis = vec![T::Quotient::from_i128(1)?];
+    for (j, &d) in leading.iter().enumerate() {
+        if j > 0 {
+            // `binomial(x, j) = binomial(x, j - 1) * (x - j + 1) / j`
+            let shift = T::Quotient::from_i128(j as i128 - 1)?;
+            let divisor = T::Quotient::from_i128(j as i128)?;
+            let mut next = vec![zero; basis.len() + 1];
+            for (i, &b) in basis.iter().enumerate() {
+                next[i + 1] = next[i + 1].checked_add(b)?;
+                next[i] = next[i].checked_sub(b.checked_mul(shift)?)?;
+            }
+            basis = next
+                .into_iter()
+                .map(|b| b.checked_div(divisor))
+                .collect::<Option<Vec<T::Quotient>>>()?;
+        }
+        let d = d.to_quotient()?;
+        for (c, &b) in coefficients.iter_mut().zip(basis.iter()) {
+            *c = c.checked_add(d.checked_mul(b)?)?;
+        }
+    }
+    Some(coefficients)
+}
+
+fn format_polynomial<F: Field>(coefficients: &[F]) -> String {
+    let terms = coefficients
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|&(_, &c)| c != F::ZERO)
+        .map(|(i, c)| {
+            let power = match i {
+                0 => String::new(),
+                1 => "x".to_string(),
+                _ => format!("x^{i}"),
+            };
+            match (c.to_string().as_str(), i) {
+                (c, 0) => c.to_string(),
+                ("1", _) => power,
+                ("-1", _) => format!("-{power}"),
+                (c, _) => format!("{c} {power}"),
+            }
         })
-        .sum::<i32>();
-    println!("The sum of these backward-extrapolated values is {sum}.");
+        .collect::<Vec<String>>();
+    if terms.is_empty() {
+        return "0".to_string();
+    }
+    let mut out = terms[0].clone();
+    for t in &terms[1..] {
+        match t.strip_prefix('-') {
+            Some(t) => out.push_str(&format!(" - {t}")),
+            None => out.push_str(&format!(" + {t}")),
+        }
+    }
+    out
+}
+
+// generalized binomial coefficient, also defined for negative `n`
+fn binomial(n: i128, k: usize) -> Option<i128> {
+    let mut b = 1_i128;
+    for i in 0..k as i128 {
+        // `b * (n - i)` is divisible by `i + 1`, as the result is
+        // `binomial(n, i + 1)`
+        b = b.checked_mul(n.checked_sub(i)?)? / (i + 1);
+    }
+    Some(b)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct OverflowError {
+    position: i128,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "extrapolating to position {} overflows", self.position)
+    }
+}
+
+impl Error for OverflowError {}
+
+// arithmetic needed by the sequence tools; every operation returns `None` if
+// the result cannot be represented, i.e. on integer overflow or once a float
+// stops being finite
+trait Number: Copy + PartialEq {
+    const ZERO: Self;
+    // relative rounding error of a single operation, zero for exact types
+    const EPSILON: f64;
+    // the smallest number type that also contains all quotients, in which
+    // closed forms are computed
+    type Quotient: Field;
+
+    fn from_i128(n: i128) -> Option<Self>;
+    fn to_quotient(self) -> Option<Self::Quotient>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    // absolute value, used to determine the `scale` for `is_zero`
+    fn magnitude(self) -> f64;
+
+    // whether `self` is zero, up to the rounding error of a computation on
+    // numbers of magnitude `scale`; exact for integers and rationals
+    fn is_zero(self, scale: f64) -> bool {
+        let magnitude = self.magnitude();
+        magnitude == 0.0 || magnitude <= Self::EPSILON * scale
+    }
+}
+
+macro_rules! impl_number_for_integer {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            const ZERO: Self = 0;
+            const EPSILON: f64 = 0.0;
+            type Quotient = Rational;
+
+            fn from_i128(n: i128) -> Option<Self> {
+                Self::try_from(n).ok()
+            }
+
+            fn to_quotient(self) -> Option<Rational> {
+                i128::try_from(self).ok().and_then(Rational::from_i128)
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+
+            fn magnitude(self) -> f64 {
+                self.unsigned_abs() as f64
+            }
+        })*
+    };
 }
 
-fn predict(v: &[i32]) -> Vec<i32> {
-    let initial_state = v[0];
-    let mut new = step(v);
-    new.insert(0, initial_state);
-    new.cumsum()
+// a number type closed under division by anything but zero
+trait Field: Number + fmt::Display {
+    fn checked_div(self, other: Self) -> Option<Self>;
 }
 
-fn step(v: &[i32]) -> Vec<i32> {
-    let diff = v.to_vec().diff();
-    if diff.iter().all(|&v| v == 0) {
-        let mut diff = diff;
-        diff.push(0);
-        diff
+// only signed types, as differences of unsigned histories are negative as
+// soon as the history decreases
+impl_number_for_integer!(i8, i16, i32, i64, i128, isize);
+
+macro_rules! impl_number_for_float {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            const ZERO: Self = 0.0;
+            const EPSILON: f64 = <$t>::EPSILON as f64;
+            type Quotient = Self;
+
+            fn from_i128(n: i128) -> Option<Self> {
+                Some(n as $t)
+            }
+
+            fn to_quotient(self) -> Option<Self> {
+                Some(self)
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                Some(self + other).filter(|r| r.is_finite())
+            }
+
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                Some(self - other).filter(|r| r.is_finite())
+            }
+
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                Some(self * other).filter(|r| r.is_finite())
+            }
+
+            fn magnitude(self) -> f64 {
+                self.abs() as f64
+            }
+        }
+
+        impl Field for $t {
+            fn checked_div(self, other: Self) -> Option<Self> {
+                Some(self / other).filter(|r| r.is_finite())
+            }
+        })*
+    };
+}
+
+impl_number_for_float!(f32, f64);
+
+// always kept in lowest terms with a positive denominator, so that equal
+// values compare equal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    fn new(numerator: i128, denominator: i128) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        let g = gcd(numerator, denominator);
+        let sign = denominator.signum();
+        Some(Self {
+            numerator: numerator.checked_div(g)?.checked_mul(sign)?,
+            denominator: denominator.checked_div(g)?.checked_mul(sign)?,
+        })
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
     } else {
-        let initial_state = diff[0];
-        let mut diff = step(&diff);
-        diff.insert(0, initial_state);
-        diff.cumsum()
+        gcd(b, a % b)
     }
 }
 
-trait Diff {
-    fn diff(&self) -> Vec<i32>;
+impl Number for Rational {
+    const ZERO: Self = Self {
+        numerator: 0,
+        denominator: 1,
+    };
+    const EPSILON: f64 = 0.0;
+    type Quotient = Self;
+
+    fn from_i128(n: i128) -> Option<Self> {
+        Some(Self {
+            numerator: n,
+            denominator: 1,
+        })
+    }
+
+    fn to_quotient(self) -> Option<Self> {
+        Some(self)
+    }
+
+    fn magnitude(self) -> f64 {
+        (self.numerator as f64 / self.denominator as f64).abs()
+    }
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Self::new(
+            self.numerator
+                .checked_mul(other.denominator)?
+                .checked_add(other.numerator.checked_mul(self.denominator)?)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(Self {
+            numerator: other.numerator.checked_neg()?,
+            denominator: other.denominator,
+        })
+    }
+
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        Self::new(
+            self.numerator.checked_mul(other.numerator)?,
+            self.denominator.checked_mul(other.denominator)?,
+        )
+    }
+}
+
+impl Field for Rational {
+    fn checked_div(self, other: Self) -> Option<Self> {
+        Self::new(
+            self.numerator.checked_mul(other.denominator)?,
+            self.denominator.checked_mul(other.numerator)?,
+        )
+    }
+}
+
+impl FromStr for Rational {
+    type Err = ParseRationalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (numerator, denominator) = s.split_once('/').unwrap_or((s, "1"));
+        let numerator = numerator
+            .parse::<i128>()
+            .map_err(|_| ParseRationalError {})?;
+        let denominator = denominator
+            .parse::<i128>()
+            .map_err(|_| ParseRationalError {})?;
+        Self::new(numerator, denominator).ok_or(ParseRationalError {})
+    }
+}
+
+#[derive(Debug)]
+struct ParseRationalError {}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+trait Diff<T> {
+    // differences of neighboring values, computed lazily; `None` marks an
+    // overflow
+    fn iter_diff(&self) -> impl Iterator<Item = Option<T>> + '_;
+    fn diff(&self) -> Option<Vec<T>>;
+    // the sequence followed by its differences of every order, down to a
+    // single value
+    fn difference_table(&self) -> Option<Vec<Vec<T>>>;
 }
 
-impl Diff for Vec<i32> {
-    fn diff(&self) -> Vec<i32> {
+impl<T: Number> Diff<T> for [T] {
+    fn iter_diff(&self) -> impl Iterator<Item = Option<T>> + '_ {
         self.windows(2)
-            .map(|values| values[1] - values[0])
-            .collect::<Vec<i32>>()
+            .map(|values| values[1].checked_sub(values[0]))
+    }
+
+    fn diff(&self) -> Option<Vec<T>> {
+        self.iter_diff().collect::<Option<Vec<T>>>()
+    }
+
+    fn difference_table(&self) -> Option<Vec<Vec<T>>> {
+        let mut table = vec![self.to_vec()];
+        while table[table.len() - 1].len() > 1 {
+            let next = table[table.len() - 1].diff()?;
+            table.push(next);
+        }
+        Some(table)
     }
 }
 
-trait CumSum {
-    fn cumsum(&self) -> Vec<i32>;
+trait CumSum<T> {
+    // running totals, computed lazily; `None` marks an overflow, after which
+    // all totals are `None`
+    fn iter_cumsum(&self) -> impl Iterator<Item = Option<T>> + '_;
+    fn cumsum(&self) -> Option<Vec<T>>;
 }
 
-impl CumSum for Vec<i32> {
-    fn cumsum(&self) -> Vec<i32> {
-        self.iter()
-            .scan(0, |acc, &x| {
-                *acc += x;
-                Some(*acc)
-            })
-            .collect::<Vec<i32>>()
+impl<T: Number> CumSum<T> for [T] {
+    fn iter_cumsum(&self) -> impl Iterator<Item = Option<T>> + '_ {
+        self.iter().scan(Some(T::ZERO), |acc, &x| {
+            *acc = acc.and_then(|acc| acc.checked_add(x));
+            Some(*acc)
+        })
+    }
+
+    fn cumsum(&self) -> Option<Vec<T>> {
+        self.iter_cumsum().collect::<Option<Vec<T>>>()
     }
 }